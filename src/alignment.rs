@@ -0,0 +1,225 @@
+use std::f64::consts::{PI, TAU};
+use uom::si::angle::radian;
+use uom::si::f64::*;
+use uom::si::time::second;
+
+use crate::orbitor::{SolarSystem, SolarSystemObject};
+use crate::search::{bisect, minimize};
+
+/// Interval used to differentiate longitudes, in seconds
+const RATE_STEP: f64 = 3600.0;
+
+/// The sweep never steps less or more than this many seconds, nor more than `INSIDE_STEP` while under the limit,
+/// so that the least value is bracketed closely
+const MIN_STEP: f64 = 600.0;
+const MAX_STEP: f64 = 10.0 * 86400.0;
+const INSIDE_STEP: f64 = 86400.0;
+
+/// How much faster than the rates at a sample the bodies are allowed to move before the next one
+const RATE_SAFETY: f64 = 2.0;
+
+/// Aspect patterns between three or four bodies
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pattern {
+    /// Three bodies 120° apart
+    GrandTrine,
+    /// Two bodies in opposition, both square to a third (the apex, listed last)
+    TSquare,
+    /// Four bodies in two oppositions square to each other
+    GrandCross,
+}
+
+impl Pattern {
+    pub fn name(self) -> &'static str {
+        match self {
+            Pattern::GrandTrine => "Grand trine",
+            Pattern::TSquare => "T-square",
+            Pattern::GrandCross => "Grand cross",
+        }
+    }
+
+    /// Pairs of positions in a list of bodies, with the angle between them
+    fn aspects(self) -> &'static [(usize, usize, f64)] {
+        match self {
+            Pattern::GrandTrine => &[(0, 1, 120.0), (1, 2, 120.0), (0, 2, 120.0)],
+            Pattern::TSquare => &[(0, 1, 180.0), (0, 2, 90.0), (1, 2, 90.0)],
+            Pattern::GrandCross => &[(0, 2, 180.0), (1, 3, 180.0), (0, 1, 90.0), (1, 2, 90.0), (2, 3, 90.0), (3, 0, 90.0)],
+        }
+    }
+}
+
+/// A stretch of time when a quantity stays under a limit
+#[derive(Debug, Clone, PartialEq)]
+pub struct Window {
+    pub start: Time,
+    pub end: Time,
+    /// When the quantity is least, and its value then
+    pub tightest: Time,
+    pub value: Angle,
+}
+
+/// Bodies gathered in a span of longitude
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cluster {
+    pub window: Window,
+    /// The bodies at the western and eastern ends of the span at the tightest
+    pub first: String,
+    pub last: String,
+}
+
+/// Bodies in an aspect pattern, within an orb of the exact angles
+#[derive(Debug, Clone, PartialEq)]
+pub struct Configuration {
+    pub pattern: Pattern,
+    pub bodies: Vec<String>,
+    /// The value is the largest departure from an exact aspect
+    pub window: Window,
+}
+
+/// Zodiac longitudes, as set by the system's equinox, of some objects
+fn longitudes(solar_system: &SolarSystem, bodies: &[&SolarSystemObject], time: Time) -> Vec<f64> {
+    bodies.iter()
+        .map(|&obj| solar_system.equinox().longitude(solar_system.observed_position(obj, time), time).get::<radian>())
+        .collect()
+}
+
+/// The difference of two longitudes, between -π and π
+fn wrap(angle: f64) -> f64 {
+    (angle + PI).rem_euclid(TAU) - PI
+}
+
+/// The shortest arc holding all the longitudes, as its length and the indices of the bodies at its ends
+fn span(longitudes: &[f64]) -> (f64, usize, usize) {
+    let mut order: Vec<usize> = (0..longitudes.len()).collect();
+    order.sort_by(|&a, &b| longitudes[a].rem_euclid(TAU).partial_cmp(&longitudes[b].rem_euclid(TAU)).unwrap());
+    // The arc is the circle less the widest gap between neighbours
+    let (gap, after) = (0..order.len())
+        .map(|i| {
+            let (here, next) = (order[i], order[(i + 1) % order.len()]);
+            ((longitudes[next] - longitudes[here]).rem_euclid(TAU), (i + 1) % order.len())
+        })
+        .fold((-1.0, 0), |best, candidate| if candidate.0 > best.0 { candidate } else { best });
+    let gap = if order.len() == 1 { TAU } else { gap };
+    (TAU - gap, order[after], order[(after + order.len() - 1) % order.len()])
+}
+
+/// Largest departure of the bodies from the exact angles of a pattern
+fn departure(pattern: Pattern, longitudes: &[f64]) -> f64 {
+    pattern.aspects().iter()
+        .map(|&(a, b, angle)| (wrap(longitudes[a] - longitudes[b]).abs() - angle.to_radians()).abs())
+        .fold(0.0, f64::max)
+}
+
+/// How fast the longitudes spread apart at most, in radians per second
+fn spread_rate(solar_system: &SolarSystem, bodies: &[&SolarSystemObject], time: Time) -> f64 {
+    let step = Time::new::<second>(RATE_STEP);
+    let (now, later) = (longitudes(solar_system, bodies, time), longitudes(solar_system, bodies, time + step));
+    let rates: Vec<f64> = now.iter().zip(&later).map(|(a, b)| wrap(b - a) / RATE_STEP).collect();
+    let max = rates.iter().copied().fold(f64::MIN, f64::max);
+    let min = rates.iter().copied().fold(f64::MAX, f64::min);
+    (max - min).max(1e-12)
+}
+
+/// Find when a quantity of some bodies' longitudes is under a limit.
+/// The quantity can change no faster than the bodies spread apart, so far from the limit the sweep takes steps as
+/// long as it would take the fastest pair to close the gap, and only slows down near it.
+fn windows(solar_system: &SolarSystem, bodies: &[&SolarSystemObject], limit: f64, start: Time, end: Time,
+           quantity: impl Fn(&[f64]) -> f64) -> Vec<Window> {
+    let f = |time: Time| quantity(&longitudes(solar_system, bodies, time)) - limit;
+    let mut found = Vec::new();
+    let mut time = start;
+    let mut value = f(time);
+    // Start of the window we're in, and its least sample so far with the times of the samples either side
+    let mut entered = (value < 0.0).then_some(start);
+    let mut least = Least { value, before: start, at: start, after: None };
+    while time < end {
+        let rate = spread_rate(solar_system, bodies, time) * RATE_SAFETY;
+        let max_step = if value < 0.0 { INSIDE_STEP } else { MAX_STEP };
+        let step = Time::new::<second>((value.abs() / rate).clamp(MIN_STEP, max_step));
+        let next = (time + step).min(end);
+        let next_value = f(next);
+        if least.at == time {
+            least.after = Some(next);
+        }
+        match (value < 0.0, next_value < 0.0) {
+            (false, true) => {
+                entered = Some(bisect(f, time, next));
+                least = Least { value: next_value, before: time, at: next, after: None };
+            },
+            (true, true) if next_value < least.value => least = Least { value: next_value, before: time, at: next, after: None },
+            (true, false) => {
+                let exit = bisect(f, time, next);
+                found.push(least.window(f, entered.take().unwrap_or(start), exit, limit));
+            },
+            _ => {},
+        }
+        time = next;
+        value = next_value;
+    }
+    if let Some(entered) = entered {
+        found.push(least.window(f, entered, end, limit));
+    }
+    found
+}
+
+/// The least sample in a window, and the times of the samples either side
+struct Least {
+    value: f64,
+    before: Time,
+    at: Time,
+    after: Option<Time>,
+}
+
+impl Least {
+    /// Refine the least value of a window between the samples either side of the least one
+    fn window(&self, f: impl Fn(Time) -> f64, start: Time, end: Time, limit: f64) -> Window {
+        let after = self.after.unwrap_or(end).min(end);
+        let tightest = minimize(&f, self.before.max(start), after);
+        Window { start, end, tightest, value: Angle::new::<radian>(f(tightest) + limit) }
+    }
+}
+
+/// Find when all the bodies fit within a span of longitude, tightest first
+pub fn clusters_between(solar_system: &SolarSystem, bodies: &[&SolarSystemObject], limit: Angle, start: Time, end: Time) -> Vec<Cluster> {
+    let mut found: Vec<Cluster> = windows(solar_system, bodies, limit.get::<radian>(), start, end, |longitudes| span(longitudes).0)
+        .into_iter()
+        .map(|window| {
+            let (_, first, last) = span(&longitudes(solar_system, bodies, window.tightest));
+            Cluster { window, first: bodies[first].get_name(), last: bodies[last].get_name() }
+        })
+        .collect();
+    found.sort_by(|a, b| a.window.value.partial_cmp(&b.window.value).unwrap());
+    found
+}
+
+/// Find grand trines, T-squares and grand crosses among the bodies, within an orb of the exact angles, tightest first
+pub fn configurations_between(solar_system: &SolarSystem, bodies: &[&SolarSystemObject], orb: Angle, start: Time, end: Time) -> Vec<Configuration> {
+    let n = bodies.len();
+    let mut candidates: Vec<(Pattern, Vec<usize>)> = Vec::new();
+    for a in 0..n {
+        for b in a + 1..n {
+            for c in b + 1..n {
+                candidates.push((Pattern::GrandTrine, vec![a, b, c]));
+                // Each of the three can be the apex
+                candidates.push((Pattern::TSquare, vec![a, b, c]));
+                candidates.push((Pattern::TSquare, vec![a, c, b]));
+                candidates.push((Pattern::TSquare, vec![b, c, a]));
+                for d in c + 1..n {
+                    // The three ways of going round four bodies
+                    candidates.push((Pattern::GrandCross, vec![a, b, c, d]));
+                    candidates.push((Pattern::GrandCross, vec![a, b, d, c]));
+                    candidates.push((Pattern::GrandCross, vec![a, c, b, d]));
+                }
+            }
+        }
+    }
+    let mut found = Vec::new();
+    for (pattern, indices) in candidates {
+        let members: Vec<&SolarSystemObject> = indices.iter().map(|&i| bodies[i]).collect();
+        for window in windows(solar_system, &members, orb.get::<radian>(), start, end, |longitudes| departure(pattern, longitudes)) {
+            found.push(Configuration { pattern, bodies: members.iter().map(|obj| obj.get_name()).collect(), window });
+        }
+    }
+    found.sort_by(|a, b| a.window.value.partial_cmp(&b.window.value).unwrap());
+    found
+}
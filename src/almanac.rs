@@ -0,0 +1,97 @@
+use uom::si::angle::degree;
+use uom::si::f64::*;
+use uom::si::time::{day, second};
+
+use crate::frames::EquatorialOfDate;
+use crate::observer::greenwich_apparent_sidereal_time;
+use crate::orbitor::{SolarSystem, SolarSystemObject};
+use crate::riseset::{crossings, transit, Crossings, Horizon};
+use crate::timescales::tt_to_ut1;
+
+/// Altitudes of the Sun's center that begin and end civil, nautical and astronomical twilight
+const CIVIL_TWILIGHT: f64 = -6.0;
+const NAUTICAL_TWILIGHT: f64 = -12.0;
+const ASTRONOMICAL_TWILIGHT: f64 = -18.0;
+
+/// The golden hour is when the Sun is between -4° and 6°, and the blue hour between -6° and -4°
+const GOLDEN_HOUR_TOP: f64 = 6.0;
+const BLUE_HOUR_TOP: f64 = -4.0;
+
+/// The Sun's events for one day at the observer. Each twilight rises at dawn and sets at dusk.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SunAlmanac {
+    pub sunrise: Crossings,
+    pub civil: Crossings,
+    pub nautical: Crossings,
+    pub astronomical: Crossings,
+    /// The Sun passing 6° up, which ends the morning golden hour and starts the evening one
+    pub golden_hour: Crossings,
+    /// The Sun passing 4° down, between the golden and blue hours
+    pub blue_hour: Crossings,
+    /// Time of the Sun's transit and its altitude then
+    pub solar_noon: Option<(Time, Angle)>,
+    /// Time the Sun is above the horizon
+    pub day_length: Time,
+    /// Equation of time and the Sun's declination at solar noon, or at the middle of the day if there isn't one
+    pub equation_of_time: Time,
+    pub declination: Angle,
+}
+
+impl SunAlmanac {
+    /// Compute the almanac for the system's observer between `start` and `end`, usually a local day
+    pub fn new(solar_system: &SolarSystem, sun: &SolarSystemObject, start: Time, end: Time) -> SunAlmanac {
+        let at = |altitude: f64| crossings(solar_system, sun, start, end, Horizon::Altitude(Angle::new::<degree>(altitude)));
+        let sunrise = crossings(solar_system, sun, start, end, Horizon::Standard);
+        let solar_noon = transit(solar_system, sun, start, end);
+        let noon = solar_noon.map_or(start + (end - start) / 2.0, |(time, _)| time);
+        SunAlmanac {
+            sunrise,
+            civil: at(CIVIL_TWILIGHT),
+            nautical: at(NAUTICAL_TWILIGHT),
+            astronomical: at(ASTRONOMICAL_TWILIGHT),
+            golden_hour: at(GOLDEN_HOUR_TOP),
+            blue_hour: at(BLUE_HOUR_TOP),
+            solar_noon,
+            day_length: sunrise.time_above(start, end),
+            equation_of_time: equation_of_time(solar_system, sun, noon),
+            declination: declination(solar_system, sun, noon),
+        }
+    }
+
+    /// A note for days without an ordinary sunrise, sunset or night
+    pub fn note(&self) -> Option<&'static str> {
+        if self.sunrise.always_above() {
+            Some("polar day")
+        }
+        else if self.sunrise.always_below() {
+            Some("polar night")
+        }
+        else if self.civil.always_above() {
+            Some("civil twilight all night")
+        }
+        else if self.nautical.always_above() {
+            Some("nautical twilight all night")
+        }
+        else if self.astronomical.always_above() {
+            Some("astronomical twilight all night")
+        }
+        else {
+            None
+        }
+    }
+}
+
+/// Apparent solar time minus mean solar time, positive when a sundial is ahead of the clock
+pub fn equation_of_time(solar_system: &SolarSystem, sun: &SolarSystemObject, time: Time) -> Time {
+    let right_ascension = solar_system.observed_position(sun, time).to::<EquatorialOfDate>(time).spherical().longitude;
+    let apparent = (greenwich_apparent_sidereal_time(time) - right_ascension).get::<degree>();
+    // The mean Sun crosses the Greenwich meridian at noon UT1, and J2000 is at noon
+    let mean = tt_to_ut1(time).get::<day>().rem_euclid(1.0) * 360.0;
+    let difference = (apparent - mean + 180.0).rem_euclid(360.0) - 180.0;
+    Time::new::<second>(difference * 240.0)
+}
+
+/// The Sun's apparent declination from the zodiac center
+pub fn declination(solar_system: &SolarSystem, sun: &SolarSystemObject, time: Time) -> Angle {
+    solar_system.observed_position(sun, time).to::<EquatorialOfDate>(time).spherical().latitude
+}
@@ -0,0 +1,110 @@
+use clap::ValueEnum;
+use uom::si::f64::*;
+use uom::si::mass::kilogram;
+use uom::si::time::second;
+
+use crate::frames::{dot, norm, unit, Cartesian, EclipticJ2000};
+use crate::orbitor::{Locatable, SolarSystemObject, G};
+
+/// Speed of light in meters per second
+pub const SPEED_OF_LIGHT: f64 = 299792458.0;
+
+/// Half-width of the interval used to differentiate the observer's position
+const VELOCITY_STEP: f64 = 60.0;
+
+/// How much of the path of light from a body to the observer to account for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum Positions {
+    /// Where the body is at the instant of observation
+    Geometric,
+    /// Where the body was when the light now arriving left it
+    Astrometric,
+    /// Astrometric, then shifted by annual aberration (and light deflection if enabled): where the body is seen
+    #[default]
+    Apparent,
+}
+
+/// Position of a body as seen by an observer.
+/// `deflector` is the body whose gravity bends the light, usually the Sun; pass `None` to leave deflection out.
+pub fn observed_position(
+    target: &SolarSystemObject,
+    observer: &SolarSystemObject,
+    time: Time,
+    positions: Positions,
+    deflector: Option<&SolarSystemObject>,
+) -> Cartesian<EclipticJ2000> {
+    // Calculated points are directions in the sky rather than bodies that emit light
+    if positions == Positions::Geometric || matches!(target, SolarSystemObject::Calculated { .. }) {
+        return target.position_from(observer, time);
+    }
+    let observer_position = observer.position(time);
+    // Star catalogues already give where the light now arriving left
+    let (relative, emitted) = match target {
+        SolarSystemObject::Star { .. } => (target.position(time) - observer_position, time),
+        _ => light_time(target, observer_position, time),
+    };
+    if positions == Positions::Astrometric || relative.norm().value == 0.0 {
+        return relative;
+    }
+    let distance = relative.norm().value;
+    let mut direction = unit(relative.meters());
+    if let Some(sun) = deflector.filter(|sun| !std::ptr::eq(*sun, target) && !std::ptr::eq(*sun, observer)) {
+        let sun_at_emission = sun.position(emitted);
+        direction = deflect(
+            direction,
+            (target.position(emitted) - sun_at_emission).meters(),
+            (observer_position - sun.position(time)).meters(),
+            sun.get_mass(),
+        );
+    }
+    let velocity = velocity(observer, time);
+    let direction = aberrate(direction, velocity.map(|v| v / SPEED_OF_LIGHT));
+    Cartesian::from_meters(direction.map(|d| d * distance))
+}
+
+/// Iterate the light travel time from the target to the observer. Returns the retarded position and the emission time.
+fn light_time(target: &SolarSystemObject, observer: Cartesian<EclipticJ2000>, time: Time) -> (Cartesian<EclipticJ2000>, Time) {
+    let mut emitted = time;
+    let mut relative = target.position(time) - observer;
+    for _ in 0..4 {
+        let delay = Time::new::<second>(relative.norm().value / SPEED_OF_LIGHT);
+        let next = time - delay;
+        relative = target.position(next) - observer;
+        let converged = (next - emitted).get::<second>().abs() < 1e-3;
+        emitted = next;
+        if converged {
+            break;
+        }
+    }
+    (relative, emitted)
+}
+
+/// Velocity of an object in meters per second, by central difference
+fn velocity(obj: &SolarSystemObject, time: Time) -> [f64; 3] {
+    let step = Time::new::<second>(VELOCITY_STEP);
+    let difference = (obj.position(time + step) - obj.position(time - step)).meters();
+    difference.map(|d| d / (2.0 * VELOCITY_STEP))
+}
+
+/// Relativistic stellar aberration of a unit direction for an observer moving at `beta` (velocity over c)
+fn aberrate(p: [f64; 3], beta: [f64; 3]) -> [f64; 3] {
+    let inverse_gamma = (1.0 - dot(beta, beta)).sqrt();
+    let p_dot_beta = dot(p, beta);
+    let w = 1.0 + p_dot_beta / (1.0 + inverse_gamma);
+    unit(std::array::from_fn(|i| (inverse_gamma * p[i] + w * beta[i]) / (1.0 + p_dot_beta)))
+}
+
+/// Bending of light passing a mass: `p` is the direction from the observer to the target,
+/// `target` and `observer` are positions relative to the deflecting body, in meters
+fn deflect(p: [f64; 3], target: [f64; 3], observer: [f64; 3], mass: Mass) -> [f64; 3] {
+    if mass.get::<kilogram>() == 0.0 {
+        return p;
+    }
+    let q = unit(target);
+    let e = unit(observer);
+    let schwarzschild_radius = 2.0 * (G * mass).value / (SPEED_OF_LIGHT * SPEED_OF_LIGHT);
+    // Keep the correction finite for bodies seen right behind the deflector
+    let w = schwarzschild_radius / norm(observer) / (1.0 + dot(q, e)).max(1e-9);
+    let (pq, pe) = (dot(p, q), dot(p, e));
+    unit(std::array::from_fn(|i| p[i] + w * (pq * e[i] - pe * q[i])))
+}
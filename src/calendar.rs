@@ -0,0 +1,240 @@
+use clap::ValueEnum;
+use time::OffsetDateTime;
+
+/// Julian date of the first day of the Gregorian calendar, 1582-10-15
+const GREGORIAN_REFORM_JD: f64 = 2299160.5;
+
+/// Julian date of the origin of Modified Julian Dates, 1858-11-17
+pub const MJD_OFFSET: f64 = 2400000.5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Calendar {
+    Julian,
+    Gregorian,
+}
+
+/// A date and time of day in the calendar in use at the time: Julian before 1582-10-15, Gregorian after.
+/// Years are astronomical, so 1 BC is year 0 and 2 BC is year -1.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CalendarDate {
+    pub year: i32,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: f64,
+}
+
+impl CalendarDate {
+    pub fn calendar(&self) -> Calendar {
+        if (self.year, self.month, self.day) < (1582, 10, 15) { Calendar::Julian } else { Calendar::Gregorian }
+    }
+
+    /// Check that the day exists, including the ten days dropped at the Gregorian reform
+    pub fn validate(&self) -> Result<(), String> {
+        let days = days_in_month(self.year, self.month, self.calendar())
+            .ok_or(format!("invalid month {}", self.month))?;
+        if self.day == 0 || self.day > days {
+            return Err(format!("{}-{:02} has no day {}", self.year, self.month, self.day));
+        }
+        if (self.year, self.month) == (1582, 10) && (5..15).contains(&self.day) {
+            return Err("1582-10-05 to 1582-10-14 were skipped by the Gregorian reform".into());
+        }
+        if self.hour > 23 || self.minute > 59 || !(0.0..61.0).contains(&self.second) {
+            return Err(format!("invalid time of day {:02}:{:02}:{}", self.hour, self.minute, self.second));
+        }
+        Ok(())
+    }
+
+    /// Julian date (Meeus ch. 7)
+    pub fn julian_date(&self) -> f64 {
+        let (mut y, mut m) = (self.year as f64, self.month as f64);
+        if m <= 2.0 {
+            y -= 1.0;
+            m += 12.0;
+        }
+        let b = match self.calendar() {
+            Calendar::Julian => 0.0,
+            Calendar::Gregorian => {
+                let a = (y / 100.0).floor();
+                2.0 - a + (a / 4.0).floor()
+            },
+        };
+        let day = self.day as f64 + (self.hour as f64 + (self.minute as f64 + self.second / 60.0) / 60.0) / 24.0;
+        (365.25 * (y + 4716.0)).floor() + (30.6001 * (m + 1.0)).floor() + day + b - 1524.5
+    }
+
+    /// Calendar date of a Julian date (Meeus ch. 7), valid from JD 0 in 4713 BC
+    pub fn from_julian_date(jd: f64) -> CalendarDate {
+        // Count whole milliseconds so times like 17:59:59.9999 print as 18:00:00
+        let milliseconds = ((jd + 0.5) * 86400000.0).round();
+        let z = (milliseconds / 86400000.0).floor();
+        let milliseconds = milliseconds - z * 86400000.0;
+        let a = if z < GREGORIAN_REFORM_JD + 0.5 {
+            z
+        }
+        else {
+            let alpha = ((z - 1867216.25) / 36524.25).floor();
+            z + 1.0 + alpha - (alpha / 4.0).floor()
+        };
+        let b = a + 1524.0;
+        let c = ((b - 122.1) / 365.25).floor();
+        let d = (365.25 * c).floor();
+        let e = ((b - d) / 30.6001).floor();
+        let day = b - d - (30.6001 * e).floor();
+        let month = if e < 14.0 { e - 1.0 } else { e - 13.0 };
+        let year = if month > 2.0 { c - 4716.0 } else { c - 4715.0 };
+        let hour = (milliseconds / 3600000.0).floor();
+        let minute = ((milliseconds - hour * 3600000.0) / 60000.0).floor();
+        CalendarDate {
+            year: year as i32,
+            month: month as u8,
+            day: day as u8,
+            hour: hour as u8,
+            minute: minute as u8,
+            second: (milliseconds - hour * 3600000.0 - minute * 60000.0) / 1000.0,
+        }
+    }
+
+    /// The same day and time some months later (or earlier), moved back to the end of the month if it is too short
+    pub fn add_months(self, months: i32) -> CalendarDate {
+        let index = self.year * 12 + self.month as i32 - 1 + months;
+        let (year, month) = (index.div_euclid(12), (index.rem_euclid(12) + 1) as u8);
+        let mut date = CalendarDate { year, month, ..self };
+        let days = days_in_month(year, month, date.calendar()).unwrap_or(31);
+        date.day = date.day.min(days);
+        date
+    }
+}
+
+impl From<OffsetDateTime> for CalendarDate {
+    /// Read the fields of a date as written, in UTC; dates before the reform are taken to be Julian
+    fn from(dt: OffsetDateTime) -> CalendarDate {
+        let dt = dt.to_offset(time::UtcOffset::UTC);
+        CalendarDate {
+            year: dt.year(),
+            month: dt.month() as u8,
+            day: dt.day(),
+            hour: dt.hour(),
+            minute: dt.minute(),
+            second: dt.second() as f64 + dt.nanosecond() as f64 * 1e-9,
+        }
+    }
+}
+
+impl std::fmt::Display for CalendarDate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let year = if self.year < 0 { format!("-{:04}", -self.year) } else { format!("{:04}", self.year) };
+        write!(f, "{year}-{:02}-{:02} {:02}:{:02}:", self.month, self.day, self.hour, self.minute)?;
+        // Seconds are truncated to whole seconds unless a precision is given, e.g. `{:.3}`
+        match f.precision() {
+            Some(precision) => {
+                let scale = 10f64.powi(precision as i32);
+                let second = (self.second * scale).floor() / scale;
+                write!(f, "{second:0width$.precision$}", width = precision + 3 - usize::from(precision == 0))?
+            },
+            None => write!(f, "{:02}", self.second.floor() as u8)?,
+        }
+        Ok(())
+    }
+}
+
+fn days_in_month(year: i32, month: u8, calendar: Calendar) -> Option<u8> {
+    let leap = match calendar {
+        Calendar::Julian => year.rem_euclid(4) == 0,
+        Calendar::Gregorian => year.rem_euclid(4) == 0 && (year.rem_euclid(100) != 0 || year.rem_euclid(400) == 0),
+    };
+    match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => Some(31),
+        4 | 6 | 9 | 11 => Some(30),
+        2 if leap => Some(29),
+        2 => Some(28),
+        _ => None,
+    }
+}
+
+/// A date written as `[-]YYYY-MM-DD`, optionally followed by `THH:MM[:SS[.fff]]` (or a space instead of `T`)
+/// and `Z` or a `±HH:MM` offset. Returns the Julian date as written, and the offset from UTC in minutes if one was given.
+pub fn parse_date(s: &str) -> Option<Result<(f64, Option<f64>), String>> {
+    let (negative, rest) = match s.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, s.strip_prefix('+').unwrap_or(s)),
+    };
+    let (date, time) = match rest.find(['T', ' ']) {
+        Some(idx) => (&rest[..idx], Some(&rest[idx + 1..])),
+        None => (rest, None),
+    };
+    let mut fields = date.split('-');
+    let (year, month, day) = (fields.next()?, fields.next()?, fields.next()?);
+    if fields.next().is_some() || year.len() < 4 || !year.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let year: i32 = year.parse().ok()?;
+    let mut date = CalendarDate {
+        year: if negative { -year } else { year },
+        month: month.parse().ok()?,
+        day: day.parse().ok()?,
+        hour: 0,
+        minute: 0,
+        second: 0.0,
+    };
+    let mut offset_minutes = None;
+    if let Some(time) = time {
+        let (clock, offset) = split_offset(time)?;
+        let mut parts = clock.split(':');
+        date.hour = parts.next()?.parse().ok()?;
+        date.minute = parts.next()?.parse().ok()?;
+        date.second = parts.next().map_or(Some(0.0), |s| s.parse().ok())?;
+        if parts.next().is_some() {
+            return None;
+        }
+        offset_minutes = offset;
+    }
+    Some(date.validate().map(|_| (date.julian_date(), offset_minutes)))
+}
+
+/// Split a trailing `Z`, `UTC` or `±HH:MM` from a time of day, returning the offset in minutes
+fn split_offset(time: &str) -> Option<(&str, Option<f64>)> {
+    if let Some(clock) = time.strip_suffix('Z').or_else(|| time.strip_suffix(" UTC")) {
+        return Some((clock, Some(0.0)));
+    }
+    match time.rfind(['+', '-']) {
+        Some(idx) => {
+            let (clock, offset) = time.split_at(idx);
+            let sign = if offset.starts_with('-') { -1.0 } else { 1.0 };
+            let (h, m) = offset[1..].split_once(':').unwrap_or((&offset[1..], "0"));
+            let minutes = h.parse::<f64>().ok()? * 60.0 + m.parse::<f64>().ok()?;
+            Some((clock, Some(sign * minutes)))
+        },
+        None => Some((time, None)),
+    }
+}
+
+/// How to write times in output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum TimeFormat {
+    /// Calendar date and time of day
+    #[default]
+    Calendar,
+    /// Julian date
+    Jd,
+    /// Modified Julian date
+    Mjd,
+}
+
+impl TimeFormat {
+    /// Write a Julian date in this format, to about a second, labelled with its time scale or zone
+    pub fn format(self, jd: f64, zone: &str) -> String {
+        match self {
+            TimeFormat::Calendar => {
+                let date = CalendarDate::from_julian_date(jd);
+                match date.calendar() {
+                    Calendar::Julian => format!("{date} {zone} (Julian)"),
+                    Calendar::Gregorian => format!("{date} {zone}"),
+                }
+            },
+            TimeFormat::Jd => format!("JD{jd:.5} {zone}"),
+            TimeFormat::Mjd => format!("MJD{:.5} {zone}", jd - MJD_OFFSET),
+        }
+    }
+}
@@ -0,0 +1,635 @@
+use std::fs;
+use std::path::Path;
+use std::rc::Rc;
+use plotters::style::RGBColor;
+use serde::{Deserialize, Serialize};
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+use uom::si::angle::{degree, radian};
+use uom::si::angular_velocity::radian_per_second;
+use uom::si::f64::*;
+use uom::si::length::{astronomical_unit, kilometer, meter};
+use uom::si::mass::kilogram;
+use uom::si::time::{day, second};
+
+use crate::lagrange::{Lagrange, LagrangePoint};
+use crate::observer::Observer;
+use crate::orbitor::{
+    internal_to_jd, Epoch, Locatable, Orbitor, Perturbations, SolarSystem, SolarSystemObject, J2000_JD, JULIAN_CENTURY,
+};
+use crate::photometry::MagnitudeLaw;
+use crate::points::CalculatedPoint;
+use crate::stars::FixedStar;
+
+/// A whole system as stored in a TOML or JSON file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SystemConfig {
+    /// Sign names, in order from 0 degrees of longitude
+    pub zodiac: Vec<String>,
+    /// Name of the object the zodiac is seen from
+    pub zodiac_center: String,
+    pub objects: Vec<ObjectConfig>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ObjectConfig {
+    pub name: String,
+    /// `#rrggbb`
+    pub color: String,
+    /// Kilograms
+    #[serde(default)]
+    pub mass: f64,
+    /// Kilometers; used for eclipses and the size of the Sun's and Moon's disks
+    #[serde(default)]
+    pub radius: f64,
+    /// Geometric albedo; with the radius, it gives a magnitude law for bodies that don't have one
+    #[serde(default)]
+    pub albedo: f64,
+    /// How the body's brightness changes with its distances and phase, e.g. `{ law = "asteroid", h = 3.34, g = 0.12 }`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub magnitude_law: Option<MagnitudeLawConfig>,
+    #[serde(flatten)]
+    pub kind: KindConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum KindConfig {
+    /// An object that doesn't move, like the Sun
+    Static {
+        /// J2000 ecliptic x, y and z
+        #[serde(default)]
+        position: [f64; 3],
+        #[serde(default)]
+        units: Units,
+    },
+    /// An object on a Keplerian orbit around an earlier object
+    Orbit {
+        parent: String,
+        elements: ElementsConfig,
+        #[serde(default)]
+        units: Units,
+    },
+    /// A star outside the system, placed by its catalogue position
+    Star {
+        /// ICRS right ascension and declination at epoch J2000, in `units.angle`
+        right_ascension: f64,
+        declination: f64,
+        /// Milliarcseconds a year, in right ascension times the cosine of declination, and in declination
+        #[serde(default)]
+        proper_motion: [f64; 2],
+        /// Milliarcseconds; stars without one are put far enough away to have none
+        #[serde(default)]
+        parallax: f64,
+        /// Visual magnitude
+        #[serde(default)]
+        magnitude: f64,
+        #[serde(default)]
+        units: Units,
+    },
+    /// A point derived from other objects, see `PointConfig`
+    Calculated {
+        #[serde(flatten)]
+        point: PointConfig,
+    },
+    /// A Lagrange point of an earlier orbiting object and its parent
+    Lagrange {
+        body: String,
+        point: LagrangeConfig,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum LagrangeConfig {
+    L1,
+    L2,
+    L3,
+    L4,
+    L5,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "point", rename_all = "snake_case")]
+pub enum PointConfig {
+    MeanNode { moon: String },
+    TrueNode { moon: String },
+    MeanApogee { moon: String },
+    PartOfFortune { sun: String, moon: String },
+    Vertex { center: String },
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(tag = "law", rename_all = "snake_case")]
+pub enum MagnitudeLawConfig {
+    Asteroid { h: f64, g: f64 },
+    Comet { h: f64, slope: f64 },
+    Mercury,
+    Venus,
+    Earth,
+    Mars,
+    Jupiter,
+    Saturn,
+    Uranus,
+    Neptune,
+    Moon,
+    Star { absolute: f64 },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ElementsConfig {
+    pub semimajor: f64,
+    pub eccentricity: f64,
+    pub inclination: f64,
+    /// Longitude of the ascending node
+    pub lan: f64,
+    /// Argument of periapsis
+    pub aop: f64,
+    /// Mean anomaly at the epoch
+    pub mean_anomaly: f64,
+    /// Julian date, or an RFC 3339 date; J2000 if left out
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub epoch: Option<EpochConfig>,
+    /// Mean motion in angle units per rate period; derived from the masses if left out
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mean_motion: Option<f64>,
+    #[serde(default)]
+    pub rates: RatesConfig,
+    /// Theory giving the position in place of the orbit, e.g. `lunar` for the Moon's
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub perturbations: Option<PerturbationsConfig>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PerturbationsConfig {
+    Lunar,
+}
+
+/// Linear change of the elements per rate period
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RatesConfig {
+    pub semimajor: f64,
+    pub eccentricity: f64,
+    pub inclination: f64,
+    pub lan: f64,
+    pub aop: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum EpochConfig {
+    JulianDate(f64),
+    DateTime(String),
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Units {
+    pub length: LengthUnit,
+    pub angle: AngleUnit,
+    pub rate_period: RatePeriod,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LengthUnit {
+    Meter,
+    Kilometer,
+    #[default]
+    Au,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AngleUnit {
+    #[default]
+    Degree,
+    Radian,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RatePeriod {
+    Day,
+    Year,
+    #[default]
+    Century,
+}
+
+impl LengthUnit {
+    fn to_length(self, value: f64) -> Length {
+        match self {
+            LengthUnit::Meter => Length::new::<meter>(value),
+            LengthUnit::Kilometer => Length::new::<kilometer>(value),
+            LengthUnit::Au => Length::new::<astronomical_unit>(value),
+        }
+    }
+
+    fn value_of(self, length: Length) -> f64 {
+        match self {
+            LengthUnit::Meter => length.get::<meter>(),
+            LengthUnit::Kilometer => length.get::<kilometer>(),
+            LengthUnit::Au => length.get::<astronomical_unit>(),
+        }
+    }
+}
+
+impl AngleUnit {
+    fn to_angle(self, value: f64) -> Angle {
+        match self {
+            AngleUnit::Degree => Angle::new::<degree>(value),
+            AngleUnit::Radian => Angle::new::<radian>(value),
+        }
+    }
+
+    fn value_of(self, angle: Angle) -> f64 {
+        match self {
+            AngleUnit::Degree => angle.get::<degree>(),
+            AngleUnit::Radian => angle.get::<radian>(),
+        }
+    }
+}
+
+impl RatePeriod {
+    fn duration(self) -> Time {
+        match self {
+            RatePeriod::Day => Time::new::<day>(1.0),
+            // Julian years, as used for element rates, rather than uom's 365-day year
+            RatePeriod::Year => Time::new::<day>(365.25),
+            RatePeriod::Century => Time::new::<day>(JULIAN_CENTURY),
+        }
+    }
+}
+
+impl Units {
+    fn angular_rate(&self, value: f64) -> AngularVelocity {
+        let rate = self.angle.to_angle(value).get::<radian>() / self.rate_period.duration().get::<second>();
+        AngularVelocity::new::<radian_per_second>(rate)
+    }
+
+    fn value_of_rate(&self, rate: AngularVelocity) -> f64 {
+        let per_period = rate.get::<radian_per_second>() * self.rate_period.duration().get::<second>();
+        self.angle.value_of(Angle::new::<radian>(per_period))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Toml,
+    Json,
+}
+
+impl ConfigFormat {
+    /// JSON for `.json` files, TOML otherwise
+    pub fn from_path(path: &Path) -> ConfigFormat {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("json") => ConfigFormat::Json,
+            _ => ConfigFormat::Toml,
+        }
+    }
+}
+
+impl SystemConfig {
+    pub fn parse(contents: &str, format: ConfigFormat) -> Result<SystemConfig, String> {
+        match format {
+            ConfigFormat::Toml => toml::from_str(contents).map_err(|e| e.to_string()),
+            ConfigFormat::Json => serde_json::from_str(contents).map_err(|e| e.to_string()),
+        }
+    }
+
+    pub fn to_string(&self, format: ConfigFormat) -> Result<String, String> {
+        match format {
+            ConfigFormat::Toml => toml::to_string(self).map_err(|e| e.to_string()),
+            ConfigFormat::Json => serde_json::to_string_pretty(self).map_err(|e| e.to_string()),
+        }
+    }
+
+    /// Describe an existing system, e.g. the default one, with elements in AU, degrees and centuries
+    pub fn from_system(solar_system: &SolarSystem) -> SystemConfig {
+        SystemConfig {
+            zodiac: solar_system.zodiac().signs().clone(),
+            zodiac_center: solar_system.zodiac_center().get_name(),
+            objects: solar_system.objects().iter().map(|obj| ObjectConfig::from_object(obj)).collect(),
+        }
+    }
+
+    /// Build the system. Parents and other referenced objects must be listed before the objects that use them.
+    pub fn build(&self, observer: Observer) -> Result<SolarSystem, String> {
+        let mut objects: Vec<Rc<SolarSystemObject>> = Vec::new();
+        for obj in &self.objects {
+            let color = parse_color(&obj.color).ok_or(format!("Invalid color {} for {}", obj.color, obj.name))?;
+            let built = match &obj.kind {
+                KindConfig::Static { position, units } => {
+                    let [x, y, z] = position.map(|p| units.length.to_length(p).get::<meter>());
+                    SolarSystemObject::new_static(&obj.name, color, obj.mass, x, y, z)
+                },
+                KindConfig::Orbit { parent, elements, units } => {
+                    let parent = find_object(&objects, parent, &obj.name)?;
+                    let orbitor = elements.build(obj.mass, parent, units)
+                        .map_err(|e| format!("{}: {e}", obj.name))?;
+                    SolarSystemObject::Orbit { name: obj.name.clone(), color, o: orbitor }
+                },
+                KindConfig::Star { right_ascension, declination, proper_motion, parallax, magnitude, units } => {
+                    let star = FixedStar::new(units.angle.to_angle(*right_ascension), units.angle.to_angle(*declination))
+                        .with_proper_motion(proper_motion[0], proper_motion[1])
+                        .with_parallax(*parallax)
+                        .with_magnitude(*magnitude);
+                    SolarSystemObject::new_star(&obj.name, color, star)
+                },
+                KindConfig::Calculated { point } => {
+                    let point = match point {
+                        PointConfig::MeanNode { moon } => CalculatedPoint::MeanNode { moon: find_moon(&objects, moon, &obj.name)? },
+                        PointConfig::TrueNode { moon } => CalculatedPoint::TrueNode { moon: find_moon(&objects, moon, &obj.name)? },
+                        PointConfig::MeanApogee { moon } => CalculatedPoint::MeanApogee { moon: find_moon(&objects, moon, &obj.name)? },
+                        PointConfig::PartOfFortune { sun, moon } => CalculatedPoint::PartOfFortune {
+                            sun: find_object(&objects, sun, &obj.name)?,
+                            moon: find_moon(&objects, moon, &obj.name)?,
+                            observer,
+                        },
+                        PointConfig::Vertex { center } => CalculatedPoint::Vertex { center: find_object(&objects, center, &obj.name)?, observer },
+                    };
+                    SolarSystemObject::new_calculated(&obj.name, color, point)
+                },
+                KindConfig::Lagrange { body, point } => {
+                    let point = LagrangePoint::new(find_object(&objects, body, &obj.name)?, point.build())
+                        .map_err(|e| format!("{}: {e}", obj.name))?;
+                    SolarSystemObject::new_lagrange(&obj.name, color, point)
+                },
+            };
+            let mut built = built.with_radius(Length::new::<kilometer>(obj.radius)).with_albedo(obj.albedo);
+            if let Some(law) = obj.magnitude_law {
+                built = built.with_magnitude(law.build());
+            }
+            objects.push(Rc::new(built));
+        }
+        let center = objects.iter()
+            .position(|o| o.get_name().eq_ignore_ascii_case(&self.zodiac_center))
+            .ok_or(format!("Zodiac center {} is not defined", self.zodiac_center))?;
+        if self.zodiac.is_empty() {
+            return Err("The zodiac needs at least one sign".into());
+        }
+        Ok(SolarSystem::new_custom(self.zodiac.clone(), objects, center))
+    }
+}
+
+fn find_object(objects: &[Rc<SolarSystemObject>], name: &str, user: &str) -> Result<Rc<SolarSystemObject>, String> {
+    objects.iter()
+        .find(|o| o.get_name().eq_ignore_ascii_case(name))
+        .cloned()
+        .ok_or(format!("{user} refers to {name}, which is not defined before it"))
+}
+
+/// Lunar points need the Moon to be on an orbit
+fn find_moon(objects: &[Rc<SolarSystemObject>], name: &str, user: &str) -> Result<Rc<SolarSystemObject>, String> {
+    let moon = find_object(objects, name, user)?;
+    match moon.orbitor() {
+        Some(_) => Ok(moon),
+        None => Err(format!("{user} needs {name} to be an orbiting object")),
+    }
+}
+
+impl LagrangeConfig {
+    fn build(self) -> Lagrange {
+        match self {
+            LagrangeConfig::L1 => Lagrange::L1,
+            LagrangeConfig::L2 => Lagrange::L2,
+            LagrangeConfig::L3 => Lagrange::L3,
+            LagrangeConfig::L4 => Lagrange::L4,
+            LagrangeConfig::L5 => Lagrange::L5,
+        }
+    }
+
+    fn from_point(point: Lagrange) -> LagrangeConfig {
+        match point {
+            Lagrange::L1 => LagrangeConfig::L1,
+            Lagrange::L2 => LagrangeConfig::L2,
+            Lagrange::L3 => LagrangeConfig::L3,
+            Lagrange::L4 => LagrangeConfig::L4,
+            Lagrange::L5 => LagrangeConfig::L5,
+        }
+    }
+}
+
+impl MagnitudeLawConfig {
+    fn build(self) -> MagnitudeLaw {
+        match self {
+            MagnitudeLawConfig::Asteroid { h, g } => MagnitudeLaw::Asteroid { h, g },
+            MagnitudeLawConfig::Comet { h, slope } => MagnitudeLaw::Comet { h, slope },
+            MagnitudeLawConfig::Mercury => MagnitudeLaw::Mercury,
+            MagnitudeLawConfig::Venus => MagnitudeLaw::Venus,
+            MagnitudeLawConfig::Earth => MagnitudeLaw::Earth,
+            MagnitudeLawConfig::Mars => MagnitudeLaw::Mars,
+            MagnitudeLawConfig::Jupiter => MagnitudeLaw::Jupiter,
+            MagnitudeLawConfig::Saturn => MagnitudeLaw::Saturn,
+            MagnitudeLawConfig::Uranus => MagnitudeLaw::Uranus,
+            MagnitudeLawConfig::Neptune => MagnitudeLaw::Neptune,
+            MagnitudeLawConfig::Moon => MagnitudeLaw::Moon,
+            MagnitudeLawConfig::Star { absolute } => MagnitudeLaw::Star { absolute },
+        }
+    }
+
+    /// Stars outside the system keep their magnitude in their own settings
+    fn from_law(law: MagnitudeLaw) -> Option<MagnitudeLawConfig> {
+        Some(match law {
+            MagnitudeLaw::Asteroid { h, g } => MagnitudeLawConfig::Asteroid { h, g },
+            MagnitudeLaw::Comet { h, slope } => MagnitudeLawConfig::Comet { h, slope },
+            MagnitudeLaw::Mercury => MagnitudeLawConfig::Mercury,
+            MagnitudeLaw::Venus => MagnitudeLawConfig::Venus,
+            MagnitudeLaw::Earth => MagnitudeLawConfig::Earth,
+            MagnitudeLaw::Mars => MagnitudeLawConfig::Mars,
+            MagnitudeLaw::Jupiter => MagnitudeLawConfig::Jupiter,
+            MagnitudeLaw::Saturn => MagnitudeLawConfig::Saturn,
+            MagnitudeLaw::Uranus => MagnitudeLawConfig::Uranus,
+            MagnitudeLaw::Neptune => MagnitudeLawConfig::Neptune,
+            MagnitudeLaw::Moon => MagnitudeLawConfig::Moon,
+            MagnitudeLaw::Star { absolute } => MagnitudeLawConfig::Star { absolute },
+            MagnitudeLaw::Fixed { .. } => return None,
+        })
+    }
+}
+
+impl ElementsConfig {
+    /// Only closed orbits can be propagated, and one bad number would turn every position into NaN
+    fn validate(&self) -> Result<(), String> {
+        let rates = &self.rates;
+        let values = [
+            ("semimajor", self.semimajor), ("eccentricity", self.eccentricity), ("inclination", self.inclination),
+            ("lan", self.lan), ("aop", self.aop), ("mean_anomaly", self.mean_anomaly),
+            ("mean_motion", self.mean_motion.unwrap_or(0.0)), ("rates.semimajor", rates.semimajor),
+            ("rates.eccentricity", rates.eccentricity), ("rates.inclination", rates.inclination),
+            ("rates.lan", rates.lan), ("rates.aop", rates.aop),
+        ];
+        if let Some((name, value)) = values.iter().find(|(_, value)| !value.is_finite()) {
+            return Err(format!("{name} is {value}, not a finite number"));
+        }
+        if let Some(EpochConfig::JulianDate(jd)) = self.epoch {
+            if !jd.is_finite() {
+                return Err(format!("epoch is {jd}, not a finite number"));
+            }
+        }
+        if !(0.0..1.0).contains(&self.eccentricity) {
+            return Err(format!("eccentricity {} is outside 0 to 1, so the orbit isn't an ellipse", self.eccentricity));
+        }
+        if self.semimajor <= 0.0 {
+            return Err(format!("semimajor axis {} is not positive", self.semimajor));
+        }
+        Ok(())
+    }
+
+    fn build(&self, mass: f64, parent: Rc<SolarSystemObject>, units: &Units) -> Result<Orbitor, String> {
+        self.validate()?;
+        let angle = |value: f64| units.angle.to_angle(value).get::<degree>();
+        let epoch = match &self.epoch {
+            None => Epoch::JulianDate(J2000_JD),
+            Some(EpochConfig::JulianDate(jd)) => Epoch::JulianDate(*jd),
+            Some(EpochConfig::DateTime(s)) => Epoch::DateTime(
+                OffsetDateTime::parse(s, &Rfc3339).map_err(|e| format!("invalid epoch {s}: {e}"))?
+            ),
+        };
+        let period = units.rate_period.duration();
+        let mut orbitor = Orbitor::new::<degree>(
+            mass,
+            parent,
+            units.length.to_length(self.semimajor).get::<meter>(),
+            self.eccentricity,
+            angle(self.inclination),
+            angle(self.lan),
+            angle(self.aop),
+            angle(self.mean_anomaly),
+        ).with_precession(
+            units.angular_rate(self.rates.lan),
+            units.angular_rate(self.rates.aop),
+        ).with_secular_rates(
+            units.length.to_length(self.rates.semimajor) / period,
+            self.rates.eccentricity / period,
+            units.angular_rate(self.rates.inclination),
+        ).with_epoch(epoch);
+        if let Some(mean_motion) = self.mean_motion {
+            orbitor = orbitor.with_mean_motion(units.angular_rate(mean_motion));
+        }
+        if let Some(PerturbationsConfig::Lunar) = self.perturbations {
+            orbitor = orbitor.with_perturbations(Perturbations::Lunar);
+        }
+        Ok(orbitor)
+    }
+
+    fn from_orbitor(o: &Orbitor, units: &Units) -> ElementsConfig {
+        let epoch = o.epoch();
+        let period = units.rate_period.duration();
+        let angle = |a: Angle| units.angle.value_of(a);
+        ElementsConfig {
+            semimajor: units.length.value_of(o.semimajor(epoch)),
+            eccentricity: o.eccentricity(epoch),
+            inclination: angle(o.inclination(epoch)),
+            lan: angle(o.lan(epoch)),
+            aop: angle(o.aop(epoch)),
+            mean_anomaly: angle(o.mean_anomaly_at_epoch()),
+            epoch: if epoch.get::<second>() == 0.0 { None } else { Some(EpochConfig::JulianDate(internal_to_jd(epoch))) },
+            mean_motion: o.mean_motion().map(|n| units.value_of_rate(n)),
+            rates: RatesConfig {
+                semimajor: units.length.value_of(o.semimajor_rate() * period),
+                eccentricity: (o.eccentricity_rate() * period).value,
+                inclination: units.value_of_rate(o.inclination_rate()),
+                lan: units.value_of_rate(o.lan_rate()),
+                aop: units.value_of_rate(o.aop_rate()),
+            },
+            perturbations: match o.perturbations() {
+                Perturbations::None => None,
+                Perturbations::Lunar => Some(PerturbationsConfig::Lunar),
+            },
+        }
+    }
+}
+
+impl ObjectConfig {
+    fn from_object(obj: &SolarSystemObject) -> ObjectConfig {
+        let units = Units::default();
+        let kind = match obj {
+            SolarSystemObject::Static { s, .. } => {
+                let p = s.position(Time::new::<second>(0.0));
+                let position = [p.x, p.y, p.z].map(|l| units.length.value_of(l));
+                KindConfig::Static { position, units }
+            },
+            SolarSystemObject::Orbit { o, .. } => KindConfig::Orbit {
+                parent: o.parent().get_name(),
+                elements: ElementsConfig::from_orbitor(o, &units),
+                units,
+            },
+            SolarSystemObject::Star { f, .. } => KindConfig::Star {
+                right_ascension: units.angle.value_of(f.right_ascension()),
+                declination: units.angle.value_of(f.declination()),
+                proper_motion: f.proper_motion(),
+                parallax: f.parallax(),
+                magnitude: f.magnitude(),
+                units,
+            },
+            SolarSystemObject::Calculated { p, .. } => KindConfig::Calculated {
+                point: match p {
+                    CalculatedPoint::MeanNode { moon } => PointConfig::MeanNode { moon: moon.get_name() },
+                    CalculatedPoint::TrueNode { moon } => PointConfig::TrueNode { moon: moon.get_name() },
+                    CalculatedPoint::MeanApogee { moon } => PointConfig::MeanApogee { moon: moon.get_name() },
+                    CalculatedPoint::PartOfFortune { sun, moon, .. } => PointConfig::PartOfFortune { sun: sun.get_name(), moon: moon.get_name() },
+                    CalculatedPoint::Vertex { center, .. } => PointConfig::Vertex { center: center.get_name() },
+                },
+            },
+            SolarSystemObject::Lagrange { l, .. } => KindConfig::Lagrange {
+                body: l.body().get_name(),
+                point: LagrangeConfig::from_point(l.point()),
+            },
+        };
+        let RGBColor(r, g, b) = obj.get_color();
+        ObjectConfig {
+            name: obj.get_name(),
+            color: format!("#{r:02x}{g:02x}{b:02x}"),
+            mass: obj.get_mass().get::<kilogram>(),
+            radius: obj.get_radius().get::<kilometer>(),
+            albedo: obj.get_albedo(),
+            magnitude_law: obj.magnitude_law().and_then(MagnitudeLawConfig::from_law),
+            kind,
+        }
+    }
+}
+
+fn parse_color(color: &str) -> Option<RGBColor> {
+    let hex = color.strip_prefix('#')?;
+    if hex.len() != 6 {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+    Some(RGBColor(channel(0)?, channel(2)?, channel(4)?))
+}
+
+/// Read a system from a `.toml` or `.json` file
+pub fn load_system(path: &Path, observer: Observer) -> Result<SolarSystem, String> {
+    let contents = fs::read_to_string(path).map_err(|e| format!("Could not read {}: {e}", path.display()))?;
+    let config = SystemConfig::parse(&contents, ConfigFormat::from_path(path))
+        .map_err(|e| format!("Could not parse {}: {e}", path.display()))?;
+    config.build(observer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The default system with one of Mercury's elements changed
+    fn with_mercury(change: impl Fn(&mut ElementsConfig)) -> Result<SolarSystem, String> {
+        let mut config = SystemConfig::from_system(&SolarSystem::new_default());
+        for obj in config.objects.iter_mut().filter(|obj| obj.name == "Mercury") {
+            if let KindConfig::Orbit { elements, .. } = &mut obj.kind {
+                change(elements);
+            }
+        }
+        config.build(Observer::default())
+    }
+
+    #[test]
+    fn rejects_orbits_that_cannot_be_propagated() {
+        assert!(with_mercury(|_| ()).is_ok());
+        assert!(with_mercury(|elements| elements.eccentricity = 1.5).err().is_some_and(|e| e.starts_with("Mercury: ")));
+        assert!(with_mercury(|elements| elements.eccentricity = -0.1).is_err());
+        assert!(with_mercury(|elements| elements.semimajor = 0.0).is_err());
+        assert!(with_mercury(|elements| elements.lan = f64::NAN).is_err());
+        assert!(with_mercury(|elements| elements.rates.aop = f64::INFINITY).is_err());
+    }
+}
@@ -0,0 +1,595 @@
+use uom::si::angle::radian;
+use uom::si::f64::*;
+use uom::si::length::meter;
+use uom::si::time::second;
+
+use crate::frames::{add, cross, norm, scale, sub, unit, Cartesian, EquatorialOfDate};
+use crate::lunar::{phases_between, LunarPhase};
+use crate::observer::{greenwich_apparent_sidereal_time, WGS84_FLATTENING};
+use crate::orbitor::{SolarSystem, SolarSystemObject};
+use crate::search::{contacts, happening, minimize};
+
+/// Greatest eclipse is looked for this many seconds either side of new or full moon
+const SEARCH_WINDOW: f64 = 4.0 * 3600.0;
+
+/// Contacts are looked for this many seconds either side of greatest eclipse, longer than any eclipse lasts
+const CONTACT_WINDOW: f64 = 5.0 * 3600.0;
+
+/// Time between the points of an eclipse's path on the map
+const PATH_STEP: f64 = 60.0;
+
+/// Rate the Earth turns, in radians per second
+const EARTH_ROTATION: f64 = 7.292115e-5;
+
+/// Enlargement of the Earth's shadow by its atmosphere, and the flattening factor applied to the Moon's parallax
+/// (Explanatory Supplement, as used by Meeus ch. 54)
+const SHADOW_ENLARGEMENT: f64 = 1.02;
+const PARALLAX_FLATTENING: f64 = 0.998340;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EclipseKind {
+    Solar,
+    Lunar,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EclipseType {
+    Total,
+    Annular,
+    /// Total along part of the path and annular along the rest
+    Hybrid,
+    Partial,
+    /// The Moon only passes through the Earth's penumbra
+    Penumbral,
+}
+
+impl EclipseType {
+    pub fn name(self) -> &'static str {
+        match self {
+            EclipseType::Total => "Total",
+            EclipseType::Annular => "Annular",
+            EclipseType::Hybrid => "Hybrid",
+            EclipseType::Partial => "Partial",
+            EclipseType::Penumbral => "Penumbral",
+        }
+    }
+}
+
+/// Times the edges of a shadow first and last touch. For solar eclipses they are contacts with the Earth as a whole,
+/// so P1 is when the eclipse begins somewhere and U1 when the umbra or antumbra first touches.
+/// For lunar eclipses they are contacts of the Moon's limb with the Earth's penumbra and umbra.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Contacts {
+    pub p1: Option<Time>,
+    pub u1: Option<Time>,
+    pub u2: Option<Time>,
+    pub u3: Option<Time>,
+    pub u4: Option<Time>,
+    pub p4: Option<Time>,
+}
+
+impl Contacts {
+    /// The global contacts that happen, from P1 to P4
+    pub fn list(&self) -> Vec<(&'static str, Time)> {
+        happening([("P1", self.p1), ("U1", self.u1), ("U2", self.u2), ("U3", self.u3), ("U4", self.u4), ("P4", self.p4)])
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Eclipse {
+    pub kind: EclipseKind,
+    pub eclipse_type: EclipseType,
+    /// Whether the axis of the Moon's shadow touches the Earth; only solar eclipses can be central
+    pub central: bool,
+    /// When the axis of the shadow passes closest to the Earth's center (solar) or the Moon's center (lunar)
+    pub greatest: Time,
+    /// Solar: fraction of the Sun's diameter covered where the eclipse is greatest.
+    /// Lunar: fraction of the Moon's diameter in the umbra, negative if it misses.
+    pub magnitude: f64,
+    /// Fraction of the Moon's diameter in the penumbra; lunar eclipses only
+    pub penumbral_magnitude: Option<f64>,
+    /// Least distance of the shadow axis from the Earth's center (solar) or the Moon's center (lunar),
+    /// in Earth radii, positive when it passes north
+    pub gamma: f64,
+    pub contacts: Contacts,
+}
+
+/// What an observer sees of a solar eclipse
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LocalEclipse {
+    /// Total, annular or partial
+    pub eclipse_type: EclipseType,
+    /// When the centers of the Sun and Moon are closest
+    pub maximum: Time,
+    /// Fraction of the Sun's diameter covered at maximum
+    pub magnitude: f64,
+    /// Fraction of the Sun's disk covered at maximum
+    pub obscuration: f64,
+    /// First and last contacts of the limbs (C1, C4), and the start and end of totality or annularity (C2, C3)
+    pub c1: Option<Time>,
+    pub c2: Option<Time>,
+    pub c3: Option<Time>,
+    pub c4: Option<Time>,
+}
+
+impl LocalEclipse {
+    /// The contacts the observer sees, leaving out C2 and C3 for a partial eclipse
+    pub fn contacts(&self) -> Vec<(&'static str, Time)> {
+        happening([("C1", self.c1), ("C2", self.c2), ("C3", self.c3), ("C4", self.c4)])
+    }
+}
+
+/// Ground track of a solar eclipse, as lines of (longitude, latitude) in degrees that don't cross the edge of a map
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct EclipsePath {
+    /// Where the axis of the shadow meets the Earth
+    pub centerline: Vec<Vec<(f64, f64)>>,
+    /// Northern and southern limits of the total or annular eclipse
+    pub umbral_limits: Vec<Vec<(f64, f64)>>,
+    /// Northern and southern limits of the partial eclipse
+    pub penumbral_limits: Vec<Vec<(f64, f64)>>,
+}
+
+/// A line on a map, broken where it leaves the Earth or wraps around in longitude
+#[derive(Default)]
+struct Track(Vec<Vec<(f64, f64)>>);
+
+impl Track {
+    fn push(&mut self, point: Option<(f64, f64)>) {
+        let Some(point) = point else {
+            if self.0.last().is_some_and(|piece| !piece.is_empty()) {
+                self.0.push(Vec::new());
+            }
+            return;
+        };
+        match self.0.last_mut() {
+            Some(piece) if piece.last().is_none_or(|last| (last.0 - point.0).abs() < 180.0) => piece.push(point),
+            _ => self.0.push(vec![point]),
+        }
+    }
+
+    fn lines(self) -> Vec<Vec<(f64, f64)>> {
+        self.0.into_iter().filter(|piece| piece.len() > 1).collect()
+    }
+}
+
+/// Shadow of a sphere cast by the Sun, on the plane through the center of the Earth perpendicular to its axis
+struct Shadow {
+    /// Direction of the axis away from the Sun, as a vector a meter long
+    axis: Cartesian<EquatorialOfDate>,
+    /// Offset of the axis from the Earth's center on that plane
+    offset: Cartesian<EquatorialOfDate>,
+    /// Radii of the penumbra and umbra on the plane. The umbra's is negative when its vertex lies beyond the plane,
+    /// where the eclipse is total; positive radii of the umbra are really the antumbra, where it is annular.
+    penumbra: f64,
+    umbra: f64,
+    /// How fast each radius shrinks towards the Earth
+    penumbra_slope: f64,
+    umbra_slope: f64,
+}
+
+impl Shadow {
+    /// The shadow of a sphere of `radius` at `moon` cast by one of `sun_radius` at `sun`, radii in meters
+    fn new(sun: Cartesian<EquatorialOfDate>, moon: Cartesian<EquatorialOfDate>, sun_radius: f64, radius: f64) -> Shadow {
+        let separation = (moon - sun).norm().get::<meter>();
+        // Unit vector along the axis, away from the Sun
+        let axis = (moon - sun) * (1.0 / separation);
+        // The Moon is on the Sun's side of the plane, `height` short of it
+        let height = -moon.dot(&axis);
+        let sin_penumbra = (sun_radius + radius) / separation;
+        let sin_umbra = (sun_radius - radius) / separation;
+        let tan = |sin: f64| sin / (1.0 - sin * sin).sqrt();
+        let cos = |sin: f64| (1.0 - sin * sin).sqrt();
+        Shadow {
+            axis,
+            offset: moon + axis * height,
+            penumbra: height * tan(sin_penumbra) + radius / cos(sin_penumbra),
+            umbra: height * tan(sin_umbra) - radius / cos(sin_umbra),
+            penumbra_slope: tan(sin_penumbra),
+            umbra_slope: tan(sin_umbra),
+        }
+    }
+
+    /// Distance of the axis from the Earth's center in meters
+    fn distance(&self) -> f64 {
+        self.offset.norm().get::<meter>()
+    }
+}
+
+/// The bodies taking part in eclipses and their radii in meters
+struct Bodies<'a> {
+    sun: &'a SolarSystemObject,
+    moon: &'a SolarSystemObject,
+    sun_radius: f64,
+    moon_radius: f64,
+    earth_radius: f64,
+}
+
+impl<'a> Bodies<'a> {
+    fn new(solar_system: &'a SolarSystem) -> Result<Bodies<'a>, String> {
+        let sun = solar_system.get("Sun").ok_or("Eclipses need an object named Sun")?;
+        let moon = solar_system.get("Moon").ok_or("Eclipses need an object named Moon")?;
+        let radius = |obj: &SolarSystemObject| {
+            let radius = obj.get_radius().get::<meter>();
+            if radius > 0.0 { Ok(radius) } else { Err(format!("Eclipses need {} to have a radius", obj.get_name())) }
+        };
+        Ok(Bodies {
+            sun,
+            moon,
+            sun_radius: radius(sun)?,
+            moon_radius: radius(moon)?,
+            earth_radius: radius(solar_system.zodiac_center())?,
+        })
+    }
+
+    /// Apparent positions of the Sun and Moon from the center of the Earth
+    fn positions(&self, solar_system: &SolarSystem, time: Time) -> (Cartesian<EquatorialOfDate>, Cartesian<EquatorialOfDate>) {
+        let position = |obj| solar_system.observed_position(obj, time).to::<EquatorialOfDate>(time);
+        (position(self.sun), position(self.moon))
+    }
+
+    /// The Moon's shadow on the Earth
+    fn moon_shadow(&self, solar_system: &SolarSystem, time: Time) -> Shadow {
+        let (sun, moon) = self.positions(solar_system, time);
+        Shadow::new(sun, moon, self.sun_radius, self.moon_radius)
+    }
+
+    /// The Moon's shadow with the Earth's polar axis stretched to make the WGS84 ellipsoid a sphere,
+    /// so the axis of the shadow meets the surface where it meets that sphere
+    fn stretched_shadow(&self, solar_system: &SolarSystem, time: Time) -> Shadow {
+        let (sun, moon) = self.positions(solar_system, time);
+        let stretch = |v: Cartesian<EquatorialOfDate>| {
+            let [x, y, z] = v.meters();
+            Cartesian::from_meters([x, y, z / (1.0 - WGS84_FLATTENING)])
+        };
+        Shadow::new(stretch(sun), stretch(moon), self.sun_radius, self.moon_radius)
+    }
+
+    /// Angular distance of the Moon's center from the axis of the Earth's shadow, the angular radii of the
+    /// penumbra and umbra at the Moon's distance, and the Moon's semidiameter, all in radians
+    fn earth_shadow(&self, solar_system: &SolarSystem, time: Time) -> (f64, f64, f64, f64) {
+        let (sun, moon) = self.positions(solar_system, time);
+        let (sun_distance, moon_distance) = (sun.norm().get::<meter>(), moon.norm().get::<meter>());
+        let moon_parallax = (self.earth_radius / moon_distance).asin();
+        let sun_parallax = (self.earth_radius / sun_distance).asin();
+        let sun_semidiameter = (self.sun_radius / sun_distance).asin();
+        let moon_semidiameter = (self.moon_radius / moon_distance).asin();
+        let penumbra = SHADOW_ENLARGEMENT * (PARALLAX_FLATTENING * moon_parallax + sun_parallax + sun_semidiameter);
+        let umbra = SHADOW_ENLARGEMENT * (PARALLAX_FLATTENING * moon_parallax + sun_parallax - sun_semidiameter);
+        let distance = moon.separation(&-sun).get::<radian>();
+        (distance, penumbra, umbra, moon_semidiameter)
+    }
+}
+
+/// Find the solar and lunar eclipses between two times, in order
+pub fn eclipses_between(solar_system: &SolarSystem, start: Time, end: Time) -> Result<Vec<Eclipse>, String> {
+    let bodies = Bodies::new(solar_system)?;
+    let mut eclipses = Vec::new();
+    for (phase, time) in phases_between(solar_system, start, end)? {
+        let eclipse = match phase {
+            LunarPhase::New => solar_eclipse(solar_system, &bodies, time),
+            LunarPhase::Full => lunar_eclipse(solar_system, &bodies, time),
+            _ => None,
+        };
+        eclipses.extend(eclipse.filter(|eclipse| eclipse.greatest >= start && eclipse.greatest < end));
+    }
+    Ok(eclipses)
+}
+
+/// The solar eclipse at a new moon, if there is one
+fn solar_eclipse(solar_system: &SolarSystem, bodies: &Bodies, new_moon: Time) -> Option<Eclipse> {
+    let shadow = |time: Time| bodies.moon_shadow(solar_system, time);
+    let window = Time::new::<second>(SEARCH_WINDOW);
+    let greatest = minimize(|time| shadow(time).distance(), new_moon - window, new_moon + window);
+    let at_greatest = shadow(greatest);
+    let earth = bodies.earth_radius;
+    let distance = at_greatest.distance();
+    if distance > earth + at_greatest.penumbra {
+        return None;
+    }
+    let gamma = (distance / earth).copysign(at_greatest.offset.meters()[2]);
+    let central = distance < earth;
+    let umbra_touches = distance < earth + at_greatest.umbra.abs();
+    let (eclipse_type, magnitude) = if central {
+        // Where the axis meets the surface, nearer the Moon than the plane
+        let height = (earth * earth - distance * distance).sqrt();
+        let penumbra = at_greatest.penumbra - height * at_greatest.penumbra_slope;
+        let umbra = at_greatest.umbra - height * at_greatest.umbra_slope;
+        let eclipse_type = match (umbra < 0.0, at_greatest.umbra < 0.0) {
+            (true, true) => EclipseType::Total,
+            (true, false) => EclipseType::Hybrid,
+            (false, _) => EclipseType::Annular,
+        };
+        (eclipse_type, (penumbra - umbra) / (penumbra + umbra))
+    }
+    else {
+        let eclipse_type = match (umbra_touches, at_greatest.umbra < 0.0) {
+            (true, true) => EclipseType::Total,
+            (true, false) => EclipseType::Annular,
+            (false, _) => EclipseType::Partial,
+        };
+        let magnitude = (at_greatest.penumbra - (distance - earth)) / (at_greatest.penumbra + at_greatest.umbra);
+        (eclipse_type, magnitude)
+    };
+    // Each contact is when the distance of the axis crosses the Earth's radius plus or minus a shadow radius
+    let contacts = |edge: &dyn Fn(&Shadow) -> f64| contacts(greatest, Time::new::<second>(CONTACT_WINDOW), |time| {
+        let shadow = shadow(time);
+        shadow.distance() - earth - edge(&shadow)
+    });
+    let (p1, p4) = contacts(&|shadow| shadow.penumbra);
+    let (u1, u4) = if umbra_touches { contacts(&|shadow| shadow.umbra.abs()) } else { (None, None) };
+    let (u2, u3) = if central { contacts(&|shadow| -shadow.umbra.abs()) } else { (None, None) };
+    Some(Eclipse {
+        kind: EclipseKind::Solar,
+        eclipse_type,
+        central,
+        greatest,
+        magnitude,
+        penumbral_magnitude: None,
+        gamma,
+        contacts: Contacts { p1, u1, u2, u3, u4, p4 },
+    })
+}
+
+/// The lunar eclipse at a full moon, if there is one
+fn lunar_eclipse(solar_system: &SolarSystem, bodies: &Bodies, full_moon: Time) -> Option<Eclipse> {
+    let shadow = |time: Time| bodies.earth_shadow(solar_system, time);
+    let window = Time::new::<second>(SEARCH_WINDOW);
+    let greatest = minimize(|time| shadow(time).0, full_moon - window, full_moon + window);
+    let (distance, penumbra, umbra, semidiameter) = shadow(greatest);
+    let penumbral_magnitude = (penumbra + semidiameter - distance) / (2.0 * semidiameter);
+    if penumbral_magnitude <= 0.0 {
+        return None;
+    }
+    let magnitude = (umbra + semidiameter - distance) / (2.0 * semidiameter);
+    let eclipse_type = if magnitude >= 1.0 {
+        EclipseType::Total
+    }
+    else if magnitude > 0.0 {
+        EclipseType::Partial
+    }
+    else {
+        EclipseType::Penumbral
+    };
+    // The Moon is north of the shadow's axis when its declination is above that of the point opposite the Sun
+    let (sun, moon) = bodies.positions(solar_system, greatest);
+    let north = moon.spherical().latitude + sun.spherical().latitude;
+    let gamma = (distance.sin() * moon.norm().get::<meter>() / bodies.earth_radius).copysign(north.value);
+    // Each contact is when the Moon's limb meets the edge of a shadow
+    let contacts = |edge: &dyn Fn(f64, f64, f64) -> f64| contacts(greatest, Time::new::<second>(CONTACT_WINDOW), |time| {
+        let (distance, penumbra, umbra, semidiameter) = shadow(time);
+        distance - edge(penumbra, umbra, semidiameter)
+    });
+    let (p1, p4) = contacts(&|penumbra, _, semidiameter| penumbra + semidiameter);
+    let (u1, u4) = if magnitude > 0.0 { contacts(&|_, umbra, semidiameter| umbra + semidiameter) } else { (None, None) };
+    let (u2, u3) = if magnitude >= 1.0 { contacts(&|_, umbra, semidiameter| umbra - semidiameter) } else { (None, None) };
+    Some(Eclipse {
+        kind: EclipseKind::Lunar,
+        eclipse_type,
+        central: false,
+        greatest,
+        magnitude,
+        penumbral_magnitude: Some(penumbral_magnitude),
+        gamma,
+        contacts: Contacts { p1, u1, u2, u3, u4, p4 },
+    })
+}
+
+/// Find what the system's observer sees of a solar eclipse, or `None` if it can't be seen from there
+/// (leaving aside whether the Sun is up)
+pub fn local_circumstances(solar_system: &SolarSystem, eclipse: &Eclipse) -> Result<Option<LocalEclipse>, String> {
+    let bodies = Bodies::new(solar_system)?;
+    // Angular distance between the centers of the Sun and Moon, and their semidiameters, in radians
+    let disks = |time: Time| {
+        let sun = solar_system.topocentric_position(bodies.sun, time);
+        let moon = solar_system.topocentric_position(bodies.moon, time);
+        let semidiameter = |position: Cartesian<_>, radius: f64| (radius / position.norm().get::<meter>()).asin();
+        (sun.separation(&moon).get::<radian>(), semidiameter(sun, bodies.sun_radius), semidiameter(moon, bodies.moon_radius))
+    };
+    let window = Time::new::<second>(CONTACT_WINDOW);
+    let start = eclipse.contacts.p1.unwrap_or(eclipse.greatest - window);
+    let end = eclipse.contacts.p4.unwrap_or(eclipse.greatest + window);
+    let maximum = minimize(|time| disks(time).0, start, end);
+    let (separation, sun, moon) = disks(maximum);
+    if separation >= sun + moon {
+        return Ok(None);
+    }
+    let eclipse_type = if separation <= moon - sun {
+        EclipseType::Total
+    }
+    else if separation <= sun - moon {
+        EclipseType::Annular
+    }
+    else {
+        EclipseType::Partial
+    };
+    let (c1, c4) = contacts(maximum, window, |time| {
+        let (separation, sun, moon) = disks(time);
+        separation - sun - moon
+    });
+    let (c2, c3) = if eclipse_type == EclipseType::Partial {
+        (None, None)
+    }
+    else {
+        contacts(maximum, window, |time| {
+            let (separation, sun, moon) = disks(time);
+            separation - (sun - moon).abs()
+        })
+    };
+    Ok(Some(LocalEclipse {
+        eclipse_type,
+        maximum,
+        magnitude: (sun + moon - separation) / (2.0 * sun),
+        obscuration: obscuration(separation, sun, moon),
+        c1,
+        c2,
+        c3,
+        c4,
+    }))
+}
+
+/// Fraction of a disk of radius `sun` covered by one of radius `moon` with their centers `separation` apart
+fn obscuration(separation: f64, sun: f64, moon: f64) -> f64 {
+    let (d, r_sun, r_moon) = (separation, sun, moon);
+    if d >= r_sun + r_moon {
+        return 0.0;
+    }
+    if d <= (r_sun - r_moon).abs() {
+        return (r_moon.min(r_sun) / r_sun).powi(2);
+    }
+    let sun_part = r_sun * r_sun * ((d * d + r_sun * r_sun - r_moon * r_moon) / (2.0 * d * r_sun)).acos();
+    let moon_part = r_moon * r_moon * ((d * d + r_moon * r_moon - r_sun * r_sun) / (2.0 * d * r_moon)).acos();
+    let kite = 0.5 * ((-d + r_sun + r_moon) * (d + r_sun - r_moon) * (d - r_sun + r_moon) * (d + r_sun + r_moon)).sqrt();
+    (sun_part + moon_part - kite) / (std::f64::consts::PI * r_sun * r_sun)
+}
+
+/// Trace the centerline and the limits of a solar eclipse across the Earth, minute by minute
+pub fn eclipse_path(solar_system: &SolarSystem, eclipse: &Eclipse) -> Result<EclipsePath, String> {
+    let bodies = Bodies::new(solar_system)?;
+    let earth = bodies.earth_radius;
+    let step = Time::new::<second>(PATH_STEP);
+    let (Some(start), Some(end)) = (eclipse.contacts.p1, eclipse.contacts.p4) else {
+        return Ok(EclipsePath::default());
+    };
+    let mut centerline = Track::default();
+    let mut umbral = [Track::default(), Track::default()];
+    let mut penumbral = [Track::default(), Track::default()];
+    let mut time = start;
+    while time <= end {
+        let shadow = bodies.stretched_shadow(solar_system, time);
+        let sidereal_time = greenwich_apparent_sidereal_time(time);
+        let center = surface_point(&shadow, shadow.offset.meters(), earth);
+        centerline.push(center.map(|point| geographic(point, sidereal_time)));
+        // The limits lie either side of the track, across the shadow's motion over the turning ground
+        let later = bodies.stretched_shadow(solar_system, time + step / 10.0);
+        let mut velocity = sub(later.offset.meters(), shadow.offset.meters());
+        if let Some([x, y, _]) = center {
+            let ground = [-y * EARTH_ROTATION * PATH_STEP / 10.0, x * EARTH_ROTATION * PATH_STEP / 10.0, 0.0];
+            velocity = sub(velocity, ground);
+        }
+        let across = unit(cross(shadow.axis.meters(), velocity));
+        for (side, sign) in [1.0, -1.0].into_iter().enumerate() {
+            let direction = scale(across, sign);
+            let point = limit(&shadow, direction, earth, |height| shadow.penumbra - height * shadow.penumbra_slope);
+            penumbral[side].push(point.map(|point| geographic(point, sidereal_time)));
+            if eclipse.eclipse_type != EclipseType::Partial {
+                let point = limit(&shadow, direction, earth, |height| (shadow.umbra - height * shadow.umbra_slope).abs());
+                umbral[side].push(point.map(|point| geographic(point, sidereal_time)));
+            }
+        }
+        time += step;
+    }
+    let [north, south] = umbral;
+    let umbral_limits = north.lines().into_iter().chain(south.lines()).collect();
+    let [north, south] = penumbral;
+    let penumbral_limits = north.lines().into_iter().chain(south.lines()).collect();
+    Ok(EclipsePath { centerline: centerline.lines(), umbral_limits, penumbral_limits })
+}
+
+/// Where the line parallel to the axis through a point of the fundamental plane meets the sunward side of the
+/// (stretched) Earth, if it does
+fn surface_point(shadow: &Shadow, plane_point: [f64; 3], earth: f64) -> Option<[f64; 3]> {
+    let distance = norm(plane_point);
+    if distance >= earth {
+        return None;
+    }
+    let height = (earth * earth - distance * distance).sqrt();
+    Some(sub(plane_point, scale(shadow.axis.meters(), height)))
+}
+
+/// Where the edge of a shadow, whose radius depends on the height above the fundamental plane, meets the Earth
+/// in a direction from the axis
+fn limit(shadow: &Shadow, direction: [f64; 3], earth: f64, radius: impl Fn(f64) -> f64) -> Option<[f64; 3]> {
+    let offset = shadow.offset.meters();
+    let mut height = 0.0;
+    let mut point = offset;
+    for _ in 0..5 {
+        point = add(offset, scale(direction, radius(height)));
+        let distance = norm(point);
+        if distance >= earth {
+            return None;
+        }
+        height = (earth * earth - distance * distance).sqrt();
+    }
+    Some(sub(point, scale(shadow.axis.meters(), height)))
+}
+
+/// Longitude and latitude in degrees of a point on the stretched Earth, on the true equator of date
+fn geographic(point: [f64; 3], sidereal_time: Angle) -> (f64, f64) {
+    let [x, y, z] = point;
+    let z = z * (1.0 - WGS84_FLATTENING);
+    let longitude = (y.atan2(x) - sidereal_time.get::<radian>()).to_degrees();
+    let latitude = (z / ((1.0 - WGS84_FLATTENING).powi(2) * x.hypot(y))).atan().to_degrees();
+    ((longitude + 180.0).rem_euclid(360.0) - 180.0, latitude)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timescales::{tt_to_utc_jd, utc_jd_to_tt};
+
+    /// The eclipse of the given kind whose greatest falls on the UTC day starting at a Julian date
+    fn eclipse_on(kind: EclipseKind, jd: f64) -> Eclipse {
+        let solar_system = SolarSystem::new_default();
+        let eclipses = eclipses_between(&solar_system, utc_jd_to_tt(jd), utc_jd_to_tt(jd + 1.0)).unwrap();
+        *eclipses.iter().find(|eclipse| eclipse.kind == kind).expect("no eclipse that day")
+    }
+
+    /// Hours past midnight UTC
+    fn utc_hours(time: Time) -> f64 {
+        (tt_to_utc_jd(time) - 0.5).fract() * 24.0
+    }
+
+    /// Reference values are from NASA's Five Millennium Canon of eclipses (Espenak and Meeus)
+    #[test]
+    fn total_solar_eclipse_of_2024() {
+        let eclipse = eclipse_on(EclipseKind::Solar, 2460408.5);
+        assert_eq!(eclipse.eclipse_type, EclipseType::Total);
+        assert!(eclipse.central);
+        assert!((utc_hours(eclipse.greatest) - 18.288).abs() < 2.0 / 60.0, "greatest at {}h", utc_hours(eclipse.greatest));
+        assert!((eclipse.magnitude - 1.0566).abs() < 0.002, "magnitude {}", eclipse.magnitude);
+        assert!((eclipse.gamma - 0.3431).abs() < 0.002, "gamma {}", eclipse.gamma);
+    }
+
+    #[test]
+    fn total_solar_eclipse_of_2017() {
+        let eclipse = eclipse_on(EclipseKind::Solar, 2457986.5);
+        assert_eq!(eclipse.eclipse_type, EclipseType::Total);
+        assert!((utc_hours(eclipse.greatest) - 18.426).abs() < 2.0 / 60.0, "greatest at {}h", utc_hours(eclipse.greatest));
+        assert!((eclipse.magnitude - 1.0306).abs() < 0.002, "magnitude {}", eclipse.magnitude);
+        assert!((eclipse.gamma - 0.4367).abs() < 0.002, "gamma {}", eclipse.gamma);
+    }
+
+    #[test]
+    fn annular_solar_eclipse_of_2024() {
+        let eclipse = eclipse_on(EclipseKind::Solar, 2460585.5);
+        assert_eq!(eclipse.eclipse_type, EclipseType::Annular);
+        assert!((utc_hours(eclipse.greatest) - 18.751).abs() < 2.0 / 60.0, "greatest at {}h", utc_hours(eclipse.greatest));
+        assert!((eclipse.magnitude - 0.9326).abs() < 0.002, "magnitude {}", eclipse.magnitude);
+        assert!((eclipse.gamma + 0.3509).abs() < 0.002, "gamma {}", eclipse.gamma);
+    }
+
+    /// Lunar magnitudes are checked more loosely, as the canon enlarges the Earth's shadow differently
+    #[test]
+    fn penumbral_lunar_eclipse_of_march_2024() {
+        let eclipse = eclipse_on(EclipseKind::Lunar, 2460394.5);
+        assert_eq!(eclipse.eclipse_type, EclipseType::Penumbral);
+        assert!((utc_hours(eclipse.greatest) - 7.214).abs() < 2.0 / 60.0, "greatest at {}h", utc_hours(eclipse.greatest));
+        assert!((eclipse.magnitude + 0.1304).abs() < 0.01, "umbral magnitude {}", eclipse.magnitude);
+        let penumbral = eclipse.penumbral_magnitude.unwrap();
+        assert!((penumbral - 0.9577).abs() < 0.03, "penumbral magnitude {penumbral}");
+        assert!((eclipse.gamma - 1.0610).abs() < 0.005, "gamma {}", eclipse.gamma);
+    }
+
+    #[test]
+    fn partial_lunar_eclipse_of_september_2024() {
+        let eclipse = eclipse_on(EclipseKind::Lunar, 2460571.5);
+        assert_eq!(eclipse.eclipse_type, EclipseType::Partial);
+        assert!((utc_hours(eclipse.greatest) - 2.738).abs() < 2.0 / 60.0, "greatest at {}h", utc_hours(eclipse.greatest));
+        assert!((eclipse.magnitude - 0.0848).abs() < 0.01, "umbral magnitude {}", eclipse.magnitude);
+        let penumbral = eclipse.penumbral_magnitude.unwrap();
+        assert!((penumbral - 1.0379).abs() < 0.03, "penumbral magnitude {penumbral}");
+        assert!((eclipse.gamma + 0.9792).abs() < 0.005, "gamma {}", eclipse.gamma);
+        assert!(eclipse.contacts.u1.is_some() && eclipse.contacts.u2.is_none());
+    }
+}
@@ -0,0 +1,216 @@
+use std::f64::consts::{PI, TAU};
+use uom::si::angle::radian;
+use uom::si::f64::*;
+use uom::si::length::meter;
+use uom::si::time::second;
+
+use crate::frames::EclipticOfDate;
+use crate::orbitor::{Locatable, SolarSystem, SolarSystemObject};
+use crate::search::{bisect, minimize};
+
+/// How often positions are sampled, in seconds; short enough to catch the Moon's perigees and approaches
+const STEP: f64 = 6.0 * 3600.0;
+
+/// What happens at an event
+#[derive(Debug, Clone, PartialEq)]
+pub enum EventKind {
+    /// Same ecliptic longitude as the Sun, for bodies outside the zodiac center's orbit
+    Conjunction,
+    /// Same longitude as the Sun, passing between it and the zodiac center
+    InferiorConjunction,
+    /// Same longitude as the Sun, on the far side of it
+    SuperiorConjunction,
+    /// Opposite the Sun in longitude
+    Opposition,
+    /// Farthest from the Sun in the sky, for bodies inside the zodiac center's orbit
+    GreatestElongation { east: bool, elongation: Angle },
+    /// Closest to and farthest from the body orbited
+    Periapsis { parent: String, distance: Length },
+    Apoapsis { parent: String, distance: Length },
+    /// Least angular distance from another body, seen from the zodiac center
+    ClosestApproach { other: String, separation: Angle },
+}
+
+/// Something that happens to a body at a moment
+#[derive(Debug, Clone, PartialEq)]
+pub struct Event {
+    pub time: Time,
+    pub body: String,
+    pub kind: EventKind,
+}
+
+impl Event {
+    pub fn name(&self) -> String {
+        match &self.kind {
+            EventKind::Conjunction => "conjunction with the Sun".into(),
+            EventKind::InferiorConjunction => "inferior conjunction".into(),
+            EventKind::SuperiorConjunction => "superior conjunction".into(),
+            EventKind::Opposition => "opposition".into(),
+            EventKind::GreatestElongation { east, .. } => {
+                format!("greatest {} elongation", if *east { "eastern" } else { "western" })
+            },
+            EventKind::Periapsis { parent, .. } => apsis_name(parent, "perigee", "perihelion", "periapsis"),
+            EventKind::Apoapsis { parent, .. } => apsis_name(parent, "apogee", "aphelion", "apoapsis"),
+            EventKind::ClosestApproach { other, .. } => format!("closest approach to {other}"),
+        }
+    }
+}
+
+/// The usual names of the apsides around the Earth and the Sun, and the general ones otherwise
+fn apsis_name(parent: &str, earth: &str, sun: &str, other: &str) -> String {
+    match parent {
+        "Earth" => earth,
+        "Sun" => sun,
+        _ => other,
+    }.into()
+}
+
+/// Times sampled every step across a range
+struct Samples {
+    start: Time,
+    step: Time,
+    count: usize,
+}
+
+impl Samples {
+    fn at(&self, i: usize) -> Time {
+        self.start + self.step * i as f64
+    }
+
+    fn values(&self, f: impl Fn(Time) -> f64) -> Vec<f64> {
+        (0..self.count).map(|i| f(self.at(i))).collect()
+    }
+
+    /// Times a function passes through zero going either way, skipping jumps where it wraps around ±π
+    fn zeros(&self, values: &[f64], f: impl Fn(Time) -> f64) -> Vec<Time> {
+        (1..values.len())
+            .filter(|&i| (values[i - 1] < 0.0) != (values[i] < 0.0) && (values[i] - values[i - 1]).abs() < PI)
+            .map(|i| bisect(&f, self.at(i - 1), self.at(i)))
+            .collect()
+    }
+
+    /// Times a function is least, between samples either side of a sampled minimum
+    fn minima(&self, values: &[f64], f: impl Fn(Time) -> f64) -> Vec<Time> {
+        (1..values.len().saturating_sub(1))
+            .filter(|&i| values[i] < values[i - 1] && values[i] <= values[i + 1])
+            .map(|i| minimize(&f, self.at(i - 1), self.at(i + 1)))
+            .collect()
+    }
+}
+
+/// Find conjunctions and oppositions with the Sun, greatest elongations, apsides and closest approaches
+/// of the named objects between two times, in order.
+/// Approaches are only listed when the two bodies come within `within` of each other.
+pub fn events_between(solar_system: &SolarSystem, names: &[String], start: Time, end: Time, within: Angle) -> Result<Vec<Event>, String> {
+    let sun = solar_system.get("Sun").ok_or("Events need an object named Sun")?;
+    let center = solar_system.zodiac_center();
+    let center_distance = |time: Time| center.orbitor().map(|orbit| orbit.semimajor(time));
+    let bodies: Vec<&SolarSystemObject> = names.iter()
+        .filter_map(|name| solar_system.get(name))
+        .filter(|obj| !matches!(obj, SolarSystemObject::Calculated { .. }))
+        .collect();
+    // Start and end a step beyond the range so that events right at its edges are bracketed
+    let step = Time::new::<second>(STEP);
+    let samples = Samples {
+        start: start - step,
+        step,
+        count: ((end - start) / step).value.ceil() as usize + 3,
+    };
+    let longitude = |obj: &SolarSystemObject, time: Time| {
+        solar_system.observed_position(obj, time).to::<EclipticOfDate>(time).spherical().longitude.get::<radian>()
+    };
+    let from_sun = |obj: &SolarSystemObject, time: Time| {
+        let wrapped = (longitude(obj, time) - longitude(sun, time) + PI).rem_euclid(TAU) - PI;
+        if wrapped == -PI { PI } else { wrapped }
+    };
+    let mut events = Vec::new();
+    for &obj in &bodies {
+        let name = obj.get_name();
+        let event = |time: Time, kind: EventKind| Event { time, body: name.clone(), kind };
+        // Apsides, for anything on an orbit
+        if let Some(orbit) = obj.orbitor() {
+            let parent = orbit.parent();
+            let distance = |time: Time| (obj.position(time) - parent.position(time)).norm().get::<meter>();
+            let values = samples.values(distance);
+            for time in samples.minima(&values, distance) {
+                let distance = Length::new::<meter>(distance(time));
+                events.push(event(time, EventKind::Periapsis { parent: parent.get_name(), distance }));
+            }
+            let negated: Vec<f64> = values.iter().map(|v| -v).collect();
+            for time in samples.minima(&negated, |time| -distance(time)) {
+                let distance = Length::new::<meter>(distance(time));
+                events.push(event(time, EventKind::Apoapsis { parent: parent.get_name(), distance }));
+            }
+        }
+        // Conjunctions, oppositions and elongations, for the bodies going around the Sun other than the zodiac center
+        let around_sun = obj.orbitor().is_some_and(|orbit| std::ptr::eq(orbit.parent().as_ref(), sun)) && !std::ptr::eq(obj, center);
+        if !around_sun {
+            continue;
+        }
+        let inner = match (obj.orbitor(), center_distance(start)) {
+            (Some(orbit), Some(center_distance)) => orbit.semimajor(start) < center_distance,
+            _ => false,
+        };
+        let difference = |time: Time| from_sun(obj, time);
+        let values = samples.values(difference);
+        for time in samples.zeros(&values, difference) {
+            let kind = if !inner {
+                EventKind::Conjunction
+            }
+            else if solar_system.observed_position(obj, time).norm() < solar_system.observed_position(sun, time).norm() {
+                EventKind::InferiorConjunction
+            }
+            else {
+                EventKind::SuperiorConjunction
+            };
+            events.push(event(time, kind));
+        }
+        if inner {
+            let elongation = |time: Time| {
+                let (body, sun) = (solar_system.observed_position(obj, time), solar_system.observed_position(sun, time));
+                -body.separation(&sun).get::<radian>()
+            };
+            let values = samples.values(elongation);
+            for time in samples.minima(&values, elongation) {
+                let east = from_sun(obj, time) > 0.0;
+                events.push(event(time, EventKind::GreatestElongation { east, elongation: Angle::new::<radian>(-elongation(time)) }));
+            }
+        }
+        else {
+            let opposite = |time: Time| {
+                let wrapped = (from_sun(obj, time) + TAU).rem_euclid(TAU) - PI;
+                if wrapped == -PI { PI } else { wrapped }
+            };
+            let values = samples.values(opposite);
+            for time in samples.zeros(&values, opposite) {
+                events.push(event(time, EventKind::Opposition));
+            }
+        }
+    }
+    // Closest approaches between pairs, leaving out the Sun, whose are the conjunctions, and the zodiac center
+    let others: Vec<&SolarSystemObject> = bodies.iter().copied()
+        .filter(|&obj| !std::ptr::eq(obj, sun) && !std::ptr::eq(obj, center))
+        .collect();
+    let positions: Vec<Vec<_>> = others.iter()
+        .map(|&obj| (0..samples.count).map(|i| solar_system.observed_position(obj, samples.at(i))).collect())
+        .collect();
+    for (i, &one) in others.iter().enumerate() {
+        for (j, &another) in others.iter().enumerate().skip(i + 1) {
+            let values: Vec<f64> = positions[i].iter().zip(&positions[j])
+                .map(|(a, b)| a.separation(b).get::<radian>())
+                .collect();
+            let separation = |time: Time| {
+                solar_system.observed_position(one, time).separation(&solar_system.observed_position(another, time)).get::<radian>()
+            };
+            for time in samples.minima(&values, separation) {
+                let separation = Angle::new::<radian>(separation(time));
+                if separation <= within {
+                    events.push(Event { time, body: one.get_name(), kind: EventKind::ClosestApproach { other: another.get_name(), separation } });
+                }
+            }
+        }
+    }
+    events.retain(|event| start <= event.time && event.time < end);
+    events.sort_by(|a, b| a.time.partial_cmp(&b.time).unwrap());
+    Ok(events)
+}
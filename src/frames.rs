@@ -0,0 +1,299 @@
+use std::marker::PhantomData;
+use std::ops::{Add, Mul, Neg, Sub};
+use clap::ValueEnum;
+use uom::si::angle::{degree, radian};
+use uom::si::f64::*;
+use uom::si::length::meter;
+use uom::si::time::second;
+
+use crate::orbitor::{normalize, Point3D, SCALING_FACTOR};
+use crate::precession::{mean_obliquity, nutation_matrix, precession_matrix, true_obliquity};
+
+/// A 3x3 rotation matrix
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Matrix3(pub [[f64; 3]; 3]);
+
+impl Matrix3 {
+    pub const IDENTITY: Matrix3 = Matrix3([[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]]);
+
+    /// Rotation of the coordinate frame by an angle about the x axis
+    pub fn r1(angle: Angle) -> Matrix3 {
+        let (s, c) = angle.get::<radian>().sin_cos();
+        Matrix3([[1.0, 0.0, 0.0], [0.0, c, s], [0.0, -s, c]])
+    }
+
+    /// Rotation of the coordinate frame by an angle about the y axis
+    pub fn r2(angle: Angle) -> Matrix3 {
+        let (s, c) = angle.get::<radian>().sin_cos();
+        Matrix3([[c, 0.0, -s], [0.0, 1.0, 0.0], [s, 0.0, c]])
+    }
+
+    /// Rotation of the coordinate frame by an angle about the z axis
+    pub fn r3(angle: Angle) -> Matrix3 {
+        let (s, c) = angle.get::<radian>().sin_cos();
+        Matrix3([[c, s, 0.0], [-s, c, 0.0], [0.0, 0.0, 1.0]])
+    }
+
+    pub fn transpose(&self) -> Matrix3 {
+        let m = self.0;
+        Matrix3(std::array::from_fn(|i| std::array::from_fn(|j| m[j][i])))
+    }
+
+    pub fn apply(&self, v: [f64; 3]) -> [f64; 3] {
+        let m = self.0;
+        std::array::from_fn(|i| m[i][0] * v[0] + m[i][1] * v[1] + m[i][2] * v[2])
+    }
+}
+
+impl Mul for Matrix3 {
+    type Output = Matrix3;
+    fn mul(self, other: Matrix3) -> Matrix3 {
+        let (a, b) = (self.0, other.0);
+        Matrix3(std::array::from_fn(|i| std::array::from_fn(|j| (0..3).map(|k| a[i][k] * b[k][j]).sum())))
+    }
+}
+
+// Plain vectors, for working in meters or in units of a radius without the frame and units of `Cartesian`
+
+pub fn add(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+pub fn sub(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+pub fn scale(a: [f64; 3], k: f64) -> [f64; 3] {
+    a.map(|x| x * k)
+}
+
+pub fn dot(a: [f64; 3], b: [f64; 3]) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+pub fn norm(a: [f64; 3]) -> f64 {
+    dot(a, a).sqrt()
+}
+
+pub fn unit(a: [f64; 3]) -> [f64; 3] {
+    scale(a, 1.0 / norm(a))
+}
+
+pub fn cross(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+}
+
+/// A reference frame, defined by its orientation relative to the mean ecliptic and equinox of J2000,
+/// which is the frame the simulation runs in. Frames only rotate; the origin of a position is up to its user.
+pub trait Frame {
+    const NAME: &'static str;
+    /// Rotation taking J2000 ecliptic coordinates into this frame
+    fn from_ecliptic_j2000(time: Time) -> Matrix3;
+}
+
+/// Mean ecliptic and equinox of J2000: the frame orbital elements are given in
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EclipticJ2000;
+
+/// Mean equator and equinox of J2000, which the ICRF matches to within a few hundredths of an arcsecond
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EquatorialJ2000;
+
+/// Ecliptic and true equinox of date: the frame of tropical zodiac longitudes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EclipticOfDate;
+
+/// True equator and equinox of date, including nutation
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EquatorialOfDate;
+
+impl Frame for EclipticJ2000 {
+    const NAME: &'static str = "ecliptic J2000";
+    fn from_ecliptic_j2000(_time: Time) -> Matrix3 {
+        Matrix3::IDENTITY
+    }
+}
+
+impl Frame for EquatorialJ2000 {
+    const NAME: &'static str = "equatorial J2000";
+    fn from_ecliptic_j2000(_time: Time) -> Matrix3 {
+        Matrix3::r1(-mean_obliquity(Time::new::<second>(0.0)))
+    }
+}
+
+impl Frame for EquatorialOfDate {
+    const NAME: &'static str = "equatorial of date";
+    fn from_ecliptic_j2000(time: Time) -> Matrix3 {
+        nutation_matrix(time) * precession_matrix(time) * EquatorialJ2000::from_ecliptic_j2000(time)
+    }
+}
+
+impl Frame for EclipticOfDate {
+    const NAME: &'static str = "ecliptic of date";
+    fn from_ecliptic_j2000(time: Time) -> Matrix3 {
+        Matrix3::r1(true_obliquity(time)) * EquatorialOfDate::from_ecliptic_j2000(time)
+    }
+}
+
+/// Which equinox ecliptic longitudes, and so zodiac signs, are measured from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum Equinox {
+    /// The equinox of J2000, fixed among the stars
+    J2000,
+    /// The true equinox of date, which moves west about 50" a year
+    #[default]
+    Date,
+}
+
+impl Equinox {
+    /// Ecliptic longitude of a J2000 ecliptic direction, measured from this equinox
+    pub fn longitude(self, v: Cartesian<EclipticJ2000>, time: Time) -> Angle {
+        match self {
+            Equinox::J2000 => v.spherical().longitude,
+            Equinox::Date => v.to::<EclipticOfDate>(time).spherical().longitude,
+        }
+    }
+
+    /// J2000 ecliptic longitude of a longitude measured from this equinox on the ecliptic
+    pub fn to_j2000(self, longitude: Angle, time: Time) -> Angle {
+        let spherical = Spherical { longitude, latitude: Angle::new::<radian>(0.0), distance: Length::new::<meter>(1.0) };
+        match self {
+            Equinox::J2000 => normalize(longitude),
+            Equinox::Date => Cartesian::<EclipticOfDate>::from_spherical(spherical).to::<EclipticJ2000>(time).spherical().longitude,
+        }
+    }
+}
+
+/// A position or direction in a given frame
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Cartesian<F: Frame> {
+    pub x: Length,
+    pub y: Length,
+    pub z: Length,
+    frame: PhantomData<F>,
+}
+
+impl<F: Frame> Cartesian<F> {
+    pub fn new(x: Length, y: Length, z: Length) -> Cartesian<F> {
+        Cartesian { x, y, z, frame: PhantomData }
+    }
+
+    pub fn zero() -> Cartesian<F> {
+        let zero = Length::new::<meter>(0.0);
+        Cartesian::new(zero, zero, zero)
+    }
+
+    pub fn from_spherical(spherical: Spherical) -> Cartesian<F> {
+        let Spherical { longitude, latitude, distance } = spherical;
+        Cartesian::new(
+            distance * latitude.cos() * longitude.cos(),
+            distance * latitude.cos() * longitude.sin(),
+            distance * latitude.sin(),
+        )
+    }
+
+    pub fn meters(&self) -> [f64; 3] {
+        [self.x.get::<meter>(), self.y.get::<meter>(), self.z.get::<meter>()]
+    }
+
+    pub fn from_meters([x, y, z]: [f64; 3]) -> Cartesian<F> {
+        Cartesian::new(Length::new::<meter>(x), Length::new::<meter>(y), Length::new::<meter>(z))
+    }
+
+    /// Rotate into another frame; `time` is needed for frames of date
+    pub fn to<G: Frame>(self, time: Time) -> Cartesian<G> {
+        let rotation = G::from_ecliptic_j2000(time) * F::from_ecliptic_j2000(time).transpose();
+        Cartesian::from_meters(rotation.apply(self.meters()))
+    }
+
+    pub fn norm(&self) -> Length {
+        (self.x * self.x + self.y * self.y + self.z * self.z).sqrt()
+    }
+
+    pub fn dot(&self, other: &Cartesian<F>) -> f64 {
+        dot(self.meters(), other.meters())
+    }
+
+    /// Angle between two directions
+    pub fn separation(&self, other: &Cartesian<F>) -> Angle {
+        let cos = self.dot(other) / (self.norm().get::<meter>() * other.norm().get::<meter>());
+        Angle::new::<radian>(cos.clamp(-1.0, 1.0).acos())
+    }
+
+    pub fn spherical(&self) -> Spherical {
+        let distance = self.norm();
+        let latitude = if distance.get::<meter>() == 0.0 {
+            Angle::new::<radian>(0.0)
+        }
+        else {
+            Angle::new::<radian>((self.z / distance).value.clamp(-1.0, 1.0).asin())
+        };
+        Spherical {
+            longitude: normalize(self.y.atan2(self.x)),
+            latitude,
+            distance,
+        }
+    }
+
+    /// Scaled-down plot coordinates, with the pole of the frame on the second axis as plotters' 3D charts expect
+    pub fn to_plot(&self) -> Point3D {
+        Point3D(self.x / SCALING_FACTOR, self.z / SCALING_FACTOR, self.y / SCALING_FACTOR)
+    }
+}
+
+impl<F: Frame> Add for Cartesian<F> {
+    type Output = Self;
+    fn add(self, other: Self) -> Self::Output {
+        Cartesian::new(self.x + other.x, self.y + other.y, self.z + other.z)
+    }
+}
+
+impl<F: Frame> Sub for Cartesian<F> {
+    type Output = Self;
+    fn sub(self, other: Self) -> Self::Output {
+        Cartesian::new(self.x - other.x, self.y - other.y, self.z - other.z)
+    }
+}
+
+impl<F: Frame> Neg for Cartesian<F> {
+    type Output = Self;
+    fn neg(self) -> Self::Output {
+        Cartesian::new(-self.x, -self.y, -self.z)
+    }
+}
+
+impl<F: Frame> Mul<f64> for Cartesian<F> {
+    type Output = Self;
+    fn mul(self, k: f64) -> Self::Output {
+        Cartesian::new(self.x * k, self.y * k, self.z * k)
+    }
+}
+
+/// Spherical coordinates. In equatorial frames the longitude is the right ascension and the latitude the declination.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Spherical {
+    pub longitude: Angle,
+    pub latitude: Angle,
+    pub distance: Length,
+}
+
+/// Format an angle as hours, minutes and seconds, for right ascension
+pub fn format_hms(angle: Angle) -> String {
+    let total = normalize(angle).get::<degree>() / 15.0 * 3600.0;
+    let total = (total * 10.0).round() / 10.0;
+    let hours = (total / 3600.0).floor();
+    let minutes = ((total - hours * 3600.0) / 60.0).floor();
+    let seconds = total - hours * 3600.0 - minutes * 60.0;
+    format!("{:02}h{:02}m{:04.1}s", hours as u32 % 24, minutes as u32, seconds)
+}
+
+/// Format an angle as signed degrees, arcminutes and arcseconds, for declination and latitude
+pub fn format_dms(angle: Angle) -> String {
+    let degrees = angle.get::<degree>();
+    let sign = if degrees < 0.0 { '-' } else { '+' };
+    let total = (degrees.abs() * 3600.0).round();
+    let d = (total / 3600.0).floor();
+    let m = ((total - d * 3600.0) / 60.0).floor();
+    let s = total - d * 3600.0 - m * 60.0;
+    format!("{sign}{:02}°{:02}'{:02}\"", d as u32, m as u32, s as u32)
+}
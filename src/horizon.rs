@@ -0,0 +1,57 @@
+use uom::si::angle::{degree, radian};
+use uom::si::f64::*;
+
+use crate::frames::{Cartesian, EquatorialOfDate};
+use crate::observer::Observer;
+use crate::orbitor::normalize;
+
+/// Compass points, starting from north and going east
+const COMPASS_POINTS: [&str; 16] = [
+    "N", "NNE", "NE", "ENE", "E", "ESE", "SE", "SSE", "S", "SSW", "SW", "WSW", "W", "WNW", "NW", "NNW",
+];
+
+/// Position in an observer's sky. Azimuth is measured from north through east.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Horizontal {
+    pub altitude: Angle,
+    pub azimuth: Angle,
+}
+
+impl Horizontal {
+    /// Altitude and azimuth of a direction seen by an observer, relative to the horizon of the WGS84 ellipsoid
+    pub fn from_equatorial(position: Cartesian<EquatorialOfDate>, observer: &Observer, time: Time) -> Horizontal {
+        let direction = position.spherical();
+        let hour_angle = (observer.local_apparent_sidereal_time(time) - direction.longitude).get::<radian>();
+        let (sin_dec, cos_dec) = direction.latitude.get::<radian>().sin_cos();
+        let (sin_lat, cos_lat) = observer.latitude.get::<radian>().sin_cos();
+        let altitude = (sin_lat * sin_dec + cos_lat * cos_dec * hour_angle.cos()).clamp(-1.0, 1.0).asin();
+        let azimuth = (-cos_dec * hour_angle.sin()).atan2(sin_dec * cos_lat - cos_dec * sin_lat * hour_angle.cos());
+        Horizontal {
+            altitude: Angle::new::<radian>(altitude),
+            azimuth: normalize(Angle::new::<radian>(azimuth)),
+        }
+    }
+
+    /// Raise the altitude by atmospheric refraction, to where the body appears
+    pub fn refracted(self) -> Horizontal {
+        Horizontal { altitude: self.altitude + refraction(self.altitude), ..self }
+    }
+
+    pub fn is_above_horizon(&self) -> bool {
+        self.altitude.get::<degree>() > 0.0
+    }
+
+    /// Nearest of the 16 compass points to the azimuth, e.g. `WSW`
+    pub fn compass_point(&self) -> &'static str {
+        let index = (self.azimuth.get::<degree>() / 22.5).round() as usize % 16;
+        COMPASS_POINTS[index]
+    }
+}
+
+/// Atmospheric refraction at a true (airless) altitude, for 1010 hPa and 10 °C (Sæmundsson, in Meeus ch. 16).
+/// Below a degree under the horizon the correction is held at its value there.
+pub fn refraction(altitude: Angle) -> Angle {
+    let h = altitude.get::<degree>().max(-1.0);
+    let arcminutes = 1.02 / (h + 10.3 / (h + 5.11)).to_radians().tan() + 0.0019279;
+    Angle::new::<degree>(arcminutes.max(0.0) / 60.0)
+}
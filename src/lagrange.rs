@@ -0,0 +1,222 @@
+use std::rc::Rc;
+use plotters::style::RGBColor;
+use uom::si::f64::*;
+use uom::si::time::second;
+
+use crate::frames::{cross, unit, Cartesian, EclipticJ2000};
+use crate::orbitor::{Locatable, SolarSystem, SolarSystemObject};
+
+/// Newton's method on the collinear points' equation stops once a step is this small, as a fraction of the separation
+const TOLERANCE: f64 = 1e-12;
+
+/// Interval used to find which way the body is moving, in seconds
+const VELOCITY_STEP: f64 = 3600.0;
+
+/// Trojans librate about L4 and L5 by up to this many degrees along the orbit, and this fraction of the distance across it
+const TROJAN_SPREAD: f64 = 30.0;
+const TROJAN_WIDTH: f64 = 0.08;
+
+/// The five equilibrium points of a body and the body it orbits
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lagrange {
+    /// Between the two bodies
+    L1,
+    /// Beyond the body, away from its parent
+    L2,
+    /// On the far side of the parent
+    L3,
+    /// 60° ahead of the body on its orbit
+    L4,
+    /// 60° behind
+    L5,
+}
+
+impl Lagrange {
+    pub const ALL: [Lagrange; 5] = [Lagrange::L1, Lagrange::L2, Lagrange::L3, Lagrange::L4, Lagrange::L5];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Lagrange::L1 => "L1",
+            Lagrange::L2 => "L2",
+            Lagrange::L3 => "L3",
+            Lagrange::L4 => "L4",
+            Lagrange::L5 => "L5",
+        }
+    }
+}
+
+/// A Lagrange point of an orbiting body and its parent, placed from where the two are at each moment
+/// as if the body were on a circular orbit at its current distance
+pub struct LagrangePoint {
+    body: Rc<SolarSystemObject>,
+    point: Lagrange,
+}
+
+impl LagrangePoint {
+    /// The point of a body on an orbit; other objects have no parent to balance against, and without a mass
+    /// L1 and L2 would fall on the body itself
+    pub fn new(body: Rc<SolarSystemObject>, point: Lagrange) -> Result<LagrangePoint, String> {
+        let orbit = body.orbitor().ok_or(format!("{} has no Lagrange points as it isn't on an orbit", body.get_name()))?;
+        if orbit.mass().value <= 0.0 || orbit.parent().get_mass().value <= 0.0 {
+            return Err(format!("{} has no Lagrange points as it or {} has no mass", body.get_name(), orbit.parent().get_name()));
+        }
+        Ok(LagrangePoint { body, point })
+    }
+
+    pub fn body(&self) -> &Rc<SolarSystemObject> {
+        &self.body
+    }
+
+    pub fn point(&self) -> Lagrange {
+        self.point
+    }
+
+    fn parent(&self) -> &SolarSystemObject {
+        self.body.orbitor().expect("Lagrange points are built from orbiting bodies").parent()
+    }
+
+    /// Outline of the region around L4 or L5 where Trojans of the body gather, going out along the orbit and back along
+    /// its inside; the other points have none
+    pub fn trojan_region(&self, time: Time, samples: usize) -> Option<Vec<Cartesian<EclipticJ2000>>> {
+        if !matches!(self.point, Lagrange::L4 | Lagrange::L5) {
+            return None;
+        }
+        let period = self.body.orbital_period(time)?;
+        let parent = self.parent().position(time);
+        // Where the point is at times around now, kept relative to where the parent is now
+        let arc: Vec<Cartesian<EclipticJ2000>> = (0..=samples)
+            .map(|i| {
+                let angle = TROJAN_SPREAD * (2.0 * i as f64 / samples as f64 - 1.0);
+                let then = time + period * (angle / 360.0);
+                self.position(then) - self.parent().position(then)
+            })
+            .collect();
+        let outer = arc.iter().map(|&offset| parent + offset * (1.0 + TROJAN_WIDTH));
+        let inner = arc.iter().rev().map(|&offset| parent + offset * (1.0 - TROJAN_WIDTH));
+        Some(outer.chain(inner).collect())
+    }
+
+    /// The body's share of the pair's mass
+    fn mass_ratio(&self) -> f64 {
+        let body = self.body.get_mass();
+        (body / (body + self.parent().get_mass())).value
+    }
+}
+
+/// Where a collinear point is along the line from the parent to the body, measured from the pair's barycenter in units
+/// of their separation: the root of the restricted three-body problem's equation nearest a starting guess
+fn collinear(point: Lagrange, mu: f64) -> f64 {
+    let hill = (mu / 3.0).cbrt();
+    let mut x = match point {
+        Lagrange::L1 => 1.0 - mu - hill,
+        Lagrange::L2 => 1.0 - mu + hill,
+        _ => -1.0 - 5.0 * mu / 12.0,
+    };
+    // Gravity of the two bodies balancing the centrifugal force in the frame turning with them
+    let force = |x: f64| {
+        let (parent, body) = (x + mu, x - 1.0 + mu);
+        x - (1.0 - mu) * parent / parent.abs().powi(3) - mu * body / body.abs().powi(3)
+    };
+    let slope = |x: f64| 1.0 + 2.0 * (1.0 - mu) / (x + mu).abs().powi(3) + 2.0 * mu / (x - 1.0 + mu).abs().powi(3);
+    for _ in 0..50 {
+        let step = force(x) / slope(x);
+        x -= step;
+        if step.abs() < TOLERANCE {
+            break;
+        }
+    }
+    x
+}
+
+impl Locatable for LagrangePoint {
+    fn position(&self, time: Time) -> Cartesian<EclipticJ2000> {
+        let parent = self.parent().position(time);
+        let separation = self.body.position(time) - parent;
+        let mu = self.mass_ratio();
+        match self.point {
+            Lagrange::L1 | Lagrange::L2 | Lagrange::L3 => parent + separation * (mu + collinear(self.point, mu)),
+            Lagrange::L4 | Lagrange::L5 => {
+                // The third corner of an equilateral triangle with the two bodies, in the plane they are moving in
+                let step = Time::new::<second>(VELOCITY_STEP);
+                let motion = (self.body.position(time + step) - self.parent().position(time + step)) - separation;
+                let pole = unit(cross(separation.meters(), motion.meters()));
+                // Along the motion, as long as the separation
+                let ahead = Cartesian::from_meters(cross(pole, separation.meters()));
+                let side = if self.point == Lagrange::L4 { 1.0 } else { -1.0 };
+                parent + separation * 0.5 + ahead * (side * 3f64.sqrt() / 2.0)
+            },
+        }
+    }
+}
+
+/// The five Lagrange points of the named body and its parent, named like `Jupiter L4`, ready to add to a system
+pub fn lagrange_points(solar_system: &SolarSystem, name: &str) -> Result<Vec<SolarSystemObject>, String> {
+    let body = solar_system.get_rc(name).ok_or(format!("Unknown object {name}"))?;
+    let RGBColor(r, g, b) = body.get_color();
+    // Dimmer than the body, so the points don't get mistaken for it
+    let color = RGBColor(r / 2 + 64, g / 2 + 64, b / 2 + 64);
+    Lagrange::ALL.iter()
+        .map(|&point| {
+            let lagrange = LagrangePoint::new(body.clone(), point)?;
+            Ok(SolarSystemObject::new_lagrange(&format!("{} {}", body.get_name(), point.name()), color, lagrange))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use plotters::style::WHITE;
+    use uom::si::length::astronomical_unit;
+    use crate::orbitor::{jd_to_internal, AU};
+
+    fn distance(a: Cartesian<EclipticJ2000>, b: Cartesian<EclipticJ2000>) -> f64 {
+        (a - b).norm().get::<astronomical_unit>()
+    }
+
+    #[test]
+    fn sun_earth_l1_and_l2_are_a_hundredth_of_an_au_out() {
+        let solar_system = SolarSystem::new_default();
+        let earth = solar_system.get_rc("Earth").unwrap();
+        let time = jd_to_internal(2460310.5);
+        for point in [Lagrange::L1, Lagrange::L2] {
+            let position = LagrangePoint::new(earth.clone(), point).unwrap().position(time);
+            let from_earth = distance(position, earth.position(time));
+            assert!((from_earth - 0.0100).abs() < 0.0002, "{} is {from_earth} AU from the Earth", point.name());
+        }
+    }
+
+    #[test]
+    fn jupiter_hill_radius() {
+        let solar_system = SolarSystem::new_default();
+        let jupiter = solar_system.get("Jupiter").unwrap().orbitor().unwrap();
+        let radius = jupiter.hill_radius(jd_to_internal(2460310.5)).get::<astronomical_unit>();
+        assert!((radius - 0.355).abs() < 0.002, "Hill radius {radius} AU");
+    }
+
+    #[test]
+    fn l4_and_l5_make_equilateral_triangles() {
+        let solar_system = SolarSystem::new_default();
+        let jupiter = solar_system.get_rc("Jupiter").unwrap();
+        let sun = solar_system.get("Sun").unwrap();
+        let time = jd_to_internal(2460310.5);
+        let separation = distance(jupiter.position(time), sun.position(time));
+        let mut corners = Vec::new();
+        for point in [Lagrange::L4, Lagrange::L5] {
+            let position = LagrangePoint::new(jupiter.clone(), point).unwrap().position(time);
+            assert!((distance(position, sun.position(time)) / separation - 1.0).abs() < 1e-9);
+            assert!((distance(position, jupiter.position(time)) / separation - 1.0).abs() < 1e-9);
+            corners.push(position);
+        }
+        // On opposite sides of the line between the two bodies
+        assert!((distance(corners[0], corners[1]) / separation - 3f64.sqrt()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn massless_bodies_have_no_points() {
+        let solar_system = SolarSystem::new_default();
+        let sun = solar_system.get_rc("Sun").unwrap();
+        let body = Rc::new(SolarSystemObject::new_orbitor("Massless", WHITE, 0.0, sun, 2.5 * AU, 0.1, 5.0, 80.0, 70.0, 10.0));
+        assert!(LagrangePoint::new(body, Lagrange::L1).is_err());
+    }
+}
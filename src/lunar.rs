@@ -0,0 +1,216 @@
+use uom::si::angle::{degree, radian};
+use uom::si::f64::*;
+use uom::si::length::kilometer;
+use uom::si::time::{day, second};
+
+use crate::frames::{EclipticOfDate, Spherical};
+use crate::orbitor::{normalize, SolarSystem, SolarSystemObject, JULIAN_CENTURY};
+use crate::search::bisect;
+
+/// Mean length of the synodic month in days
+pub const SYNODIC_MONTH: f64 = 29.530588861;
+
+/// Spacing of the samples searched for phases; the elongation grows about 3° in this time
+const STEP: f64 = 6.0 * 3600.0;
+
+/// Mean distance of the Moon in kilometers
+const MEAN_DISTANCE: f64 = 385000.56;
+
+/// Periodic terms of the Moon's longitude and distance from Meeus table 47.A, as multiples of D, M, M' and F,
+/// then millionths of a degree and meters
+const LONGITUDE_DISTANCE_TERMS: [(i8, i8, i8, i8, f64, f64); 60] = [
+    (0, 0, 1, 0, 6288774.0, -20905355.0), (2, 0, -1, 0, 1274027.0, -3699111.0), (2, 0, 0, 0, 658314.0, -2955968.0),
+    (0, 0, 2, 0, 213618.0, -569925.0), (0, 1, 0, 0, -185116.0, 48888.0), (0, 0, 0, 2, -114332.0, -3149.0),
+    (2, 0, -2, 0, 58793.0, 246158.0), (2, -1, -1, 0, 57066.0, -152138.0), (2, 0, 1, 0, 53322.0, -170733.0),
+    (2, -1, 0, 0, 45758.0, -204586.0), (0, 1, -1, 0, -40923.0, -129620.0), (1, 0, 0, 0, -34720.0, 108743.0),
+    (0, 1, 1, 0, -30383.0, 104755.0), (2, 0, 0, -2, 15327.0, 10321.0), (0, 0, 1, 2, -12528.0, 0.0),
+    (0, 0, 1, -2, 10980.0, 79661.0), (4, 0, -1, 0, 10675.0, -34782.0), (0, 0, 3, 0, 10034.0, -23210.0),
+    (4, 0, -2, 0, 8548.0, -21636.0), (2, 1, -1, 0, -7888.0, 24208.0), (2, 1, 0, 0, -6766.0, 30824.0),
+    (1, 0, -1, 0, -5163.0, -8379.0), (1, 1, 0, 0, 4987.0, -16675.0), (2, -1, 1, 0, 4036.0, -12831.0),
+    (2, 0, 2, 0, 3994.0, -10445.0), (4, 0, 0, 0, 3861.0, -11650.0), (2, 0, -3, 0, 3665.0, 14403.0),
+    (0, 1, -2, 0, -2689.0, -7003.0), (2, 0, -1, 2, -2602.0, 0.0), (2, -1, -2, 0, 2390.0, 10056.0),
+    (1, 0, 1, 0, -2348.0, 6322.0), (2, -2, 0, 0, 2236.0, -9884.0), (0, 1, 2, 0, -2120.0, 5751.0),
+    (0, 2, 0, 0, -2069.0, 0.0), (2, -2, -1, 0, 2048.0, -4950.0), (2, 0, 1, -2, -1773.0, 4130.0),
+    (2, 0, 0, 2, -1595.0, 0.0), (4, -1, -1, 0, 1215.0, -3958.0), (0, 0, 2, 2, -1110.0, 0.0),
+    (3, 0, -1, 0, -892.0, 3258.0), (2, 1, 1, 0, -810.0, 2616.0), (4, -1, -2, 0, 759.0, -1897.0),
+    (0, 2, -1, 0, -713.0, -2117.0), (2, 2, -1, 0, -700.0, 2354.0), (2, 1, -2, 0, 691.0, 0.0),
+    (2, -1, 0, -2, 596.0, 0.0), (4, 0, 1, 0, 549.0, -1423.0), (0, 0, 4, 0, 537.0, -1117.0),
+    (4, -1, 0, 0, 520.0, -1571.0), (1, 0, -2, 0, -487.0, -1739.0), (2, 1, 0, -2, -399.0, 0.0),
+    (0, 0, 2, -2, -381.0, -4421.0), (1, 1, 1, 0, 351.0, 0.0), (3, 0, -2, 0, -340.0, 0.0),
+    (4, 0, -3, 0, 330.0, 0.0), (2, -1, 2, 0, 327.0, 0.0), (0, 2, 1, 0, -323.0, 1165.0),
+    (1, 1, -1, 0, 299.0, 0.0), (2, 0, 3, 0, 294.0, 0.0), (2, 0, -1, -2, 0.0, 8752.0),
+];
+
+/// Periodic terms of the Moon's latitude from Meeus table 47.B, in millionths of a degree
+const LATITUDE_TERMS: [(i8, i8, i8, i8, f64); 60] = [
+    (0, 0, 0, 1, 5128122.0), (0, 0, 1, 1, 280602.0), (0, 0, 1, -1, 277693.0), (2, 0, 0, -1, 173237.0),
+    (2, 0, -1, 1, 55413.0), (2, 0, -1, -1, 46271.0), (2, 0, 0, 1, 32573.0), (0, 0, 2, 1, 17198.0),
+    (2, 0, 1, -1, 9266.0), (0, 0, 2, -1, 8822.0), (2, -1, 0, -1, 8216.0), (2, 0, -2, -1, 4324.0),
+    (2, 0, 1, 1, 4200.0), (2, 1, 0, -1, -3359.0), (2, -1, -1, 1, 2463.0), (2, -1, 0, 1, 2211.0),
+    (2, -1, -1, -1, 2065.0), (0, 1, -1, -1, -1870.0), (4, 0, -1, -1, 1828.0), (0, 1, 0, 1, -1794.0),
+    (0, 0, 0, 3, -1749.0), (0, 1, -1, 1, -1565.0), (1, 0, 0, 1, -1491.0), (0, 1, 1, 1, -1475.0),
+    (0, 1, 1, -1, -1410.0), (0, 1, 0, -1, -1344.0), (1, 0, 0, -1, -1335.0), (0, 0, 3, 1, 1107.0),
+    (4, 0, 0, -1, 1021.0), (4, 0, -1, 1, 833.0), (0, 0, 1, -3, 777.0), (4, 0, -2, 1, 671.0),
+    (2, 0, 0, -3, 607.0), (2, 0, 2, -1, 596.0), (2, -1, 1, -1, 491.0), (2, 0, -2, 1, -451.0),
+    (0, 0, 3, -1, 439.0), (2, 0, 2, 1, 422.0), (2, 0, -3, -1, 421.0), (2, 1, -1, 1, -366.0),
+    (2, 1, 0, 1, -351.0), (4, 0, 0, 1, 331.0), (2, -1, 1, 1, 315.0), (2, -2, 0, -1, 302.0),
+    (0, 0, 1, 3, -283.0), (2, 1, 1, -1, -229.0), (1, 1, 0, -1, 223.0), (1, 1, 0, 1, 223.0),
+    (0, 1, -2, -1, -220.0), (2, 1, -1, -1, -220.0), (1, 0, 1, 1, -185.0), (2, -1, -2, -1, 181.0),
+    (0, 1, 2, 1, -177.0), (4, 0, -2, -1, 176.0), (4, -1, -1, -1, 166.0), (1, 0, 1, -1, -164.0),
+    (4, 0, 1, -1, 132.0), (1, 0, -1, -1, -119.0), (4, -1, 0, -1, 115.0), (2, -2, 0, 1, 107.0),
+];
+
+/// The four principal phases, when the Moon's ecliptic longitude is 0°, 90°, 180° or 270° from the Sun's
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LunarPhase {
+    New,
+    FirstQuarter,
+    Full,
+    LastQuarter,
+}
+
+impl LunarPhase {
+    pub const ALL: [LunarPhase; 4] = [LunarPhase::New, LunarPhase::FirstQuarter, LunarPhase::Full, LunarPhase::LastQuarter];
+
+    pub fn elongation(self) -> Angle {
+        Angle::new::<degree>(match self {
+            LunarPhase::New => 0.0,
+            LunarPhase::FirstQuarter => 90.0,
+            LunarPhase::Full => 180.0,
+            LunarPhase::LastQuarter => 270.0,
+        })
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            LunarPhase::New => "New Moon",
+            LunarPhase::FirstQuarter => "First Quarter",
+            LunarPhase::Full => "Full Moon",
+            LunarPhase::LastQuarter => "Last Quarter",
+        }
+    }
+}
+
+/// The Moon as seen from the zodiac center at one time
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MoonState {
+    /// Ecliptic longitude of the Moon minus that of the Sun, from 0° at new moon through 180° at full
+    pub elongation: Angle,
+    /// Angle at the Moon between the Sun and the observer: 0° when full, 180° when new
+    pub phase_angle: Angle,
+    /// Fraction of the disk that is lit
+    pub illuminated: f64,
+    /// Time since the last new moon
+    pub age: Time,
+}
+
+impl MoonState {
+    /// Name of the phase, with each principal phase covering the eighth of the month around it
+    pub fn phase_name(&self) -> &'static str {
+        const NAMES: [&str; 8] = [
+            "New Moon", "Waxing Crescent", "First Quarter", "Waxing Gibbous",
+            "Full Moon", "Waning Gibbous", "Last Quarter", "Waning Crescent",
+        ];
+        NAMES[(self.elongation.get::<degree>() / 45.0).round() as usize % 8]
+    }
+}
+
+fn sun_and_moon(solar_system: &SolarSystem) -> Result<(&SolarSystemObject, &SolarSystemObject), String> {
+    let sun = solar_system.get("Sun").ok_or("Moon phases need an object named Sun")?;
+    let moon = solar_system.get("Moon").ok_or("Moon phases need an object named Moon")?;
+    Ok((sun, moon))
+}
+
+/// Elongation of the Moon from the Sun in ecliptic longitude, as seen from the zodiac center
+fn elongation(solar_system: &SolarSystem, sun: &SolarSystemObject, moon: &SolarSystemObject, time: Time) -> Angle {
+    let longitude = |obj| solar_system.observed_position(obj, time).to::<EclipticOfDate>(time).spherical().longitude;
+    normalize(longitude(moon) - longitude(sun))
+}
+
+/// The Moon's geometric position from the center of the Earth, on the ecliptic and mean equinox of date,
+/// from the main terms of its theory (Meeus ch. 47)
+pub fn geocentric_position(time: Time) -> Spherical {
+    let t = time.get::<day>() / JULIAN_CENTURY;
+    let polynomial = |coefficients: [f64; 4]| {
+        (coefficients[0] + t * (coefficients[1] + t * (coefficients[2] + t * coefficients[3]))).to_radians()
+    };
+    let mean_longitude = polynomial([218.3164477, 481267.88123421, -0.0015786, 1.0 / 538841.0]);
+    let elongation = polynomial([297.8501921, 445267.1114034, -0.0018819, 1.0 / 545868.0]);
+    let sun_anomaly = polynomial([357.5291092, 35999.0502909, -0.0001536, 1.0 / 24490000.0]);
+    let moon_anomaly = polynomial([134.9633964, 477198.8675055, 0.0087414, 1.0 / 69699.0]);
+    let latitude_argument = polynomial([93.2720950, 483202.0175233, -0.0036539, -1.0 / 3526000.0]);
+    // Terms in the Sun's anomaly shrink with the eccentricity of the Earth's orbit
+    let eccentricity = 1.0 - 0.002516 * t - 0.0000074 * t * t;
+    let argument = |d: i8, m: i8, mm: i8, f: i8| {
+        let scale = eccentricity.powi(m.unsigned_abs() as i32);
+        let angle = d as f64 * elongation + m as f64 * sun_anomaly + mm as f64 * moon_anomaly + f as f64 * latitude_argument;
+        (angle, scale)
+    };
+    let (mut longitude, mut distance) = (0.0, 0.0);
+    for (d, m, mm, f, l, r) in LONGITUDE_DISTANCE_TERMS {
+        let (angle, scale) = argument(d, m, mm, f);
+        longitude += l * scale * angle.sin();
+        distance += r * scale * angle.cos();
+    }
+    let mut latitude = 0.0;
+    for (d, m, mm, f, b) in LATITUDE_TERMS {
+        let (angle, scale) = argument(d, m, mm, f);
+        latitude += b * scale * angle.sin();
+    }
+    // Venus, Jupiter and the flattening of the Earth
+    let a1 = (119.75 + 131.849 * t).to_radians();
+    let a2 = (53.09 + 479264.290 * t).to_radians();
+    let a3 = (313.45 + 481266.484 * t).to_radians();
+    longitude += 3958.0 * a1.sin() + 1962.0 * (mean_longitude - latitude_argument).sin() + 318.0 * a2.sin();
+    latitude += -2235.0 * mean_longitude.sin() + 382.0 * a3.sin() + 175.0 * (a1 - latitude_argument).sin()
+        + 175.0 * (a1 + latitude_argument).sin() + 127.0 * (mean_longitude - moon_anomaly).sin()
+        - 115.0 * (mean_longitude + moon_anomaly).sin();
+    Spherical {
+        longitude: normalize(Angle::new::<radian>(mean_longitude) + Angle::new::<degree>(longitude * 1e-6)),
+        latitude: Angle::new::<degree>(latitude * 1e-6),
+        distance: Length::new::<kilometer>(MEAN_DISTANCE + distance * 1e-3),
+    }
+}
+
+/// Phase, illumination and age of the Moon at a time
+pub fn moon_state(solar_system: &SolarSystem, time: Time) -> Result<MoonState, String> {
+    let (sun, moon) = sun_and_moon(solar_system)?;
+    let moon_position = solar_system.observed_position(moon, time);
+    let sun_position = solar_system.observed_position(sun, time);
+    let phase_angle = (sun_position - moon_position).separation(&-moon_position);
+    let month = Time::new::<day>(SYNODIC_MONTH + 1.0);
+    let last_new = phases_between(solar_system, time - month, time)?.into_iter()
+        .rev()
+        .find(|(phase, _)| *phase == LunarPhase::New)
+        .map(|(_, time)| time);
+    Ok(MoonState {
+        elongation: elongation(solar_system, sun, moon, time),
+        phase_angle,
+        illuminated: (1.0 + phase_angle.get::<radian>().cos()) / 2.0,
+        age: last_new.map_or(Time::new::<second>(0.0), |new| time - new),
+    })
+}
+
+/// Times of the principal phases between two times, in order
+pub fn phases_between(solar_system: &SolarSystem, start: Time, end: Time) -> Result<Vec<(LunarPhase, Time)>, String> {
+    let (sun, moon) = sun_and_moon(solar_system)?;
+    // How far past a phase the Moon is, between -180° and 180°
+    let past = |phase: LunarPhase, time: Time| {
+        let difference = (elongation(solar_system, sun, moon, time) - phase.elongation()).get::<degree>();
+        (difference + 180.0).rem_euclid(360.0) - 180.0
+    };
+    let step = Time::new::<second>(STEP);
+    let mut phases = Vec::new();
+    let mut t0 = start;
+    while t0 < end {
+        let t1 = if t0 + step > end { end } else { t0 + step };
+        for phase in LunarPhase::ALL {
+            let (p0, p1) = (past(phase, t0), past(phase, t1));
+            if p0 < 0.0 && p1 >= 0.0 && p1 - p0 < 180.0 {
+                phases.push((phase, bisect(|time| past(phase, time), t0, t1)));
+            }
+        }
+        t0 = t1;
+    }
+    Ok(phases)
+}
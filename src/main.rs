@@ -1,34 +1,101 @@
-use std::time::SystemTime;
-use orbitor::dt_to_internal;
-use time::{
-    format_description::well_known::{Iso8601, Rfc2822, Rfc3339}, macros::format_description, Date, OffsetDateTime
-};
+use std::path::PathBuf;
 use clap::*;
-use uom::si::{angle::radian, f64::*, length::meter, time::second};
+use time_tz::{TimeZone, Tz};
+use uom::si::{angle::{degree, radian}, f64::*, length::{astronomical_unit, meter}, time::second};
 use plotters::{prelude::*,  style::full_palette::GREY};
 
+mod alignment;
+mod almanac;
+mod apparent;
+mod calendar;
+mod config;
+mod eclipse;
+mod events;
+mod frames;
+mod horizon;
+mod lagrange;
+mod lunar;
+mod mpc;
+mod observer;
+mod occultation;
 mod orbitor;
+mod photometry;
+mod points;
+mod precession;
+mod riseset;
+mod search;
+mod seasons;
+mod stars;
+mod timeinput;
+mod timescales;
+mod timezone;
 
+use crate::almanac::SunAlmanac;
+use crate::apparent::Positions;
+use crate::calendar::{Calendar, CalendarDate, TimeFormat, MJD_OFFSET};
+use crate::config::{ConfigFormat, SystemConfig};
+use crate::eclipse::{EclipseKind, EclipsePath, EclipseType};
+use crate::events::EventKind;
+use crate::frames::{
+    format_dms, format_hms, Equinox, EclipticJ2000, EclipticOfDate, EquatorialJ2000, EquatorialOfDate, Frame, Spherical,
+};
+use crate::lagrange::{Lagrange, LagrangePoint};
+use crate::mpc::{MpcFilter, MpcFormat};
+use crate::observer::Observer;
+use crate::occultation::PassageKind;
+use crate::riseset::RiseTransitSet;
 use crate::orbitor::{
-    SolarSystem,
+    SolarSystem, SolarSystemObject,
     Locatable,
     Point2D, Point3D,
     // deg_to_rad,
 };
+use crate::timeinput::{parse_time, TimeInput};
+use crate::timescales::{tt_to_utc_jd, TimeScale};
+
+/// How times are written in output
+#[derive(Debug, Clone, Copy)]
+struct TimeWriter {
+    format: TimeFormat,
+    zone: &'static Tz,
+}
+
+impl TimeWriter {
+    /// Calendar dates are written in the zone, Julian dates always in UTC
+    fn write(&self, time: Time) -> String {
+        let jd = tt_to_utc_jd(time);
+        match self.format {
+            TimeFormat::Calendar => {
+                let (offset, abbreviation) = timezone::offset_at(self.zone, jd);
+                self.format.format(jd + offset / 86400.0, &abbreviation)
+            },
+            TimeFormat::Jd | TimeFormat::Mjd => self.format.format(jd, "UTC"),
+        }
+    }
 
-fn parse_time(time_str: &str) -> Result<OffsetDateTime, String> {
-    if let Ok(time) = OffsetDateTime::parse(time_str, &Iso8601::DEFAULT) {
-        Ok(time)
-    } else if let Ok(time) = OffsetDateTime::parse(time_str, &Rfc2822) {
-        Ok(time)
-    } else if let Ok(time) = OffsetDateTime::parse(time_str, &Rfc3339) {
-        Ok(time)
-    } else if let Ok(date) = Date::parse(time_str, format_description!("[year]-[month]-[day]")) {
-        Ok(date.midnight().assume_utc())
-    } else if time_str == "now" {
-        Ok(SystemTime::now().into())
-    } else {
-        Err("Unknown".into())
+    /// Just the time of day in the zone, for tables that give the date separately; Julian dates are written in full
+    fn write_clock(&self, time: Time) -> String {
+        match self.format {
+            TimeFormat::Calendar => {
+                let date = CalendarDate::from_julian_date(timezone::wall(self.zone, time));
+                format!("{:02}:{:02}:{:02}", date.hour, date.minute, date.second.floor() as u8)
+            },
+            TimeFormat::Jd | TimeFormat::Mjd => self.write(time),
+        }
+    }
+
+    fn write_clock_or_dashes(&self, time: Option<Time>) -> String {
+        time.map_or("--:--:--".to_string(), |time| self.write_clock(time))
+    }
+
+    /// The local days starting with the one containing a time, as the instants they start
+    fn days(&self, time: Time, count: u32) -> Result<Vec<Time>, String> {
+        let first = timezone::midnight(timezone::wall(self.zone, time));
+        (0..=count).map(|day| {
+            let midnight = first + day as f64;
+            // A few zones skip midnight when clocks go forward, so the day starts an hour later
+            timezone::from_wall(self.zone, midnight).or_else(|_| timezone::from_wall(self.zone, midnight + 1.0 / 24.0))
+        }).collect()
     }
 }
 
@@ -59,6 +126,17 @@ pub fn plot_2d(solar_system: &SolarSystem, pixels: u32, scale: f64, time: Time)
             Into::<ShapeStyle>::into(GREY).stroke_width(stroke_width_base),
         )).unwrap();
     }
+    for obj in solar_system.objects() {
+        let SolarSystemObject::Lagrange { l, .. } = obj.as_ref() else {
+            continue;
+        };
+        if let Some(region) = l.trojan_region(time, 60) {
+            chart.draw_series(std::iter::once(Polygon::new(
+                region.into_iter().map(|p| Point2D::from(p.to_plot()).loc()).collect::<Vec<_>>(),
+                obj.get_color().mix(0.5).filled(),
+            ))).unwrap();
+        }
+    }
     for obj in solar_system.objects() {
         let (ox, oy) = obj.xy(time).loc();
         chart.draw_series(PointSeries::of_element(
@@ -92,8 +170,14 @@ pub fn plot_2d(solar_system: &SolarSystem, pixels: u32, scale: f64, time: Time)
     }
 }
 
-pub fn plot_rel_2d(solar_system: &SolarSystem, pixels: u32, scale: f64, start_time: Time) {
-    
+/// How long the trajectories of objects without a period of their own are drawn for in relative plots
+fn relative_period(solar_system: &SolarSystem, time: Time) -> Result<Time, String> {
+    let center = solar_system.zodiac_center();
+    center.orbital_period(time).ok_or(format!("{} has no orbit to plot relative to", center.get_name()))
+}
+
+pub fn plot_rel_2d(solar_system: &SolarSystem, pixels: u32, scale: f64, start_time: Time) -> Result<(), String> {
+    let center_period = relative_period(solar_system, start_time)?;
     let stroke_width_base = (pixels / 2048).max(1);
     println!("Drawing 2d relative...");
 
@@ -149,7 +233,7 @@ pub fn plot_rel_2d(solar_system: &SolarSystem, pixels: u32, scale: f64, start_ti
             let stroke_width = if obj.get_name() == "Moon" {1} else {2};
             let end_time = match obj.orbital_period(time) {
                 Some(op) => time + op,
-                None => time + solar_system.zodiac_center().orbital_period(time).unwrap()
+                None => time + center_period,
             };
             let trajectory: Vec<Point2D> = obj.trajectory_relative(solar_system.zodiac_center(), time, end_time, 100)
                 .into_iter().map(|x| x.into()).collect();
@@ -160,6 +244,7 @@ pub fn plot_rel_2d(solar_system: &SolarSystem, pixels: u32, scale: f64, start_ti
         }
         root_drawing_area.present().unwrap();
     }
+    Ok(())
 }
 
 pub fn plot_3d(solar_system: &SolarSystem, pixels: u32, scale: f64, time: Time) {
@@ -221,7 +306,8 @@ pub fn plot_3d(solar_system: &SolarSystem, pixels: u32, scale: f64, time: Time)
     }
 }
 
-pub fn plot_rel_3d(solar_system: &SolarSystem, pixels: u32, scale: f64, time: Time) {
+pub fn plot_rel_3d(solar_system: &SolarSystem, pixels: u32, scale: f64, time: Time) -> Result<(), String> {
+    let center_period = relative_period(solar_system, time)?;
     let stroke_width_base = (pixels / 2048).max(1);
     let offset = solar_system.zodiac_center().xyz(time);
     println!("Drawing 3d relative...");
@@ -268,7 +354,7 @@ pub fn plot_rel_3d(solar_system: &SolarSystem, pixels: u32, scale: f64, time: Ti
         let stroke_width = if obj.get_name() == "Moon" {1} else {2};
         let end_time = match obj.orbital_period(time) {
             Some(op) => time + op,
-            None => time + solar_system.zodiac_center().orbital_period(time).unwrap()
+            None => time + center_period,
         };
         let trajectory: Vec<Point3D> = obj.trajectory_relative(solar_system.zodiac_center(), time, end_time, 100);
         chart.draw_series(LineSeries::new(
@@ -278,19 +364,151 @@ pub fn plot_rel_3d(solar_system: &SolarSystem, pixels: u32, scale: f64, time: Ti
             Into::<ShapeStyle>::into(obj.get_color()).stroke_width(stroke_width_base * stroke_width),
         )).unwrap();
     }
+    Ok(())
 }
 
-fn print_next_sign_time(solar_system: &SolarSystem, planets: Vec<String>, sign: &String, start_time: OffsetDateTime) {
-    println!("Calculating next time for {sign} starting from {start_time}:");
+/// Draw where the Sun is in the observer's sky at the time of day of `time` on each day of the following year
+fn plot_analemma(solar_system: &SolarSystem, pixels: u32, time: Time, writer: TimeWriter) -> Result<(), String> {
+    const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+    let stroke_width_base = (pixels / 2048).max(1);
+    println!("Drawing analemma...");
+    let wall = timezone::wall(writer.zone, time);
+    let first = timezone::midnight(wall);
+    let mut points = Vec::new();
+    // Whole days apart, which holds mean (standard) time fixed; clocks put forward for daylight saving time would
+    // shift part of the figure by an hour
+    for day in 0..365 {
+        let time = time + Time::new::<second>(day as f64 * 86400.0);
+        let horizontal = solar_system.horizontal_for("Sun", time).ok_or("The analemma needs an object named Sun")?;
+        points.push((horizontal.azimuth.get::<degree>(), horizontal.altitude.get::<degree>(), CalendarDate::from_julian_date(first + day as f64)));
+    }
+    // Keep the azimuths continuous around their mean, which may be due north
+    let (sin, cos) = points.iter().fold((0.0, 0.0), |(sin, cos), &(azimuth, _, _)| {
+        (sin + azimuth.to_radians().sin(), cos + azimuth.to_radians().cos())
+    });
+    let center = sin.atan2(cos).to_degrees();
+    let points: Vec<(f64, f64, CalendarDate)> = points.into_iter()
+        .map(|(azimuth, altitude, date)| (center + (azimuth - center + 180.0).rem_euclid(360.0) - 180.0, altitude, date))
+        .collect();
+    // Degrees of azimuth and altitude are drawn the same size, so the figure eight keeps its shape
+    let bounds = |values: Vec<f64>| values.iter().fold((f64::MAX, f64::MIN), |(low, high), &v| (low.min(v), high.max(v)));
+    let (x0, x1) = bounds(points.iter().map(|p| p.0).collect());
+    let (y0, y1) = bounds(points.iter().map(|p| p.1).collect());
+    let half = (x1 - x0).max(y1 - y0) * 0.55 + 1.0;
+    let (x, y) = ((x0 + x1) / 2.0, (y0 + y1) / 2.0);
+    let observer = solar_system.observer();
+    let clock = match writer.format {
+        TimeFormat::Calendar => {
+            let offset = timezone::standard_offset(writer.zone, tt_to_utc_jd(time));
+            let date = CalendarDate::from_julian_date(tt_to_utc_jd(time) + offset / 86400.0);
+            format!("{:02}:{:02} {} (standard time)", date.hour, date.minute, timezone::format_offset(offset))
+        },
+        TimeFormat::Jd | TimeFormat::Mjd => writer.write(time),
+    };
+    let caption = format!("Sun at {clock} from {} {}", format_dms(observer.latitude), format_dms(observer.longitude));
+    let root_drawing_area = BitMapBackend::new("images/analemma.png", (pixels, pixels)).into_drawing_area();
+    root_drawing_area.fill(&BLACK).map_err(|e| e.to_string())?;
+    let mut chart = ChartBuilder::on(&root_drawing_area).margin(20).caption(caption, ("sans-serif", 20).into_font().color(&WHITE))
+        .x_label_area_size(40)
+        .y_label_area_size(50)
+        .build_cartesian_2d(x - half..x + half, y - half..y + half)
+        .map_err(|e| e.to_string())?;
+    chart.configure_mesh()
+        .x_desc("Azimuth (°)")
+        .y_desc("Altitude (°)")
+        .axis_style(GREY)
+        .bold_line_style(GREY.mix(0.3))
+        .light_line_style(TRANSPARENT)
+        .label_style(("sans-serif", 14).into_font().color(&WHITE))
+        .draw()
+        .map_err(|e| e.to_string())?;
+    chart.draw_series(LineSeries::new(vec![(x - half, 0.0), (x + half, 0.0)], Into::<ShapeStyle>::into(GREY).stroke_width(stroke_width_base)))
+        .map_err(|e| e.to_string())?;
+    chart.draw_series(PointSeries::of_element(
+        points.iter().map(|&(azimuth, altitude, _)| (azimuth, altitude)),
+        stroke_width_base * 3,
+        Into::<ShapeStyle>::into(YELLOW).filled(),
+        &|coord, size, style| EmptyElement::at(coord) + Circle::new((0, 0), size, style),
+    )).map_err(|e| e.to_string())?;
+    for &(azimuth, altitude, date) in points.iter().filter(|(_, _, date)| date.day == 1) {
+        let label = Text::new(MONTHS[date.month as usize - 1], (10, -10), ("sans-serif", 16).into_font().color(&WHITE));
+        chart.draw_series(std::iter::once(
+            EmptyElement::at((azimuth, altitude)) + Circle::new((0, 0), stroke_width_base * 6, Into::<ShapeStyle>::into(WHITE).filled()) + label
+        )).map_err(|e| e.to_string())?;
+    }
+    root_drawing_area.present().map_err(|e| e.to_string())?;
+    println!("Analemma written to images/analemma.png");
+    Ok(())
+}
+
+/// Coasts to draw under eclipse maps, as lines of (longitude, latitude) in degrees
+fn world_outline() -> Vec<Vec<(f64, f64)>> {
+    let mut lines = vec![Vec::new()];
+    for line in include_str!("../data/world_outline.txt").lines().filter(|line| !line.starts_with('#')) {
+        let mut numbers = line.split_whitespace().filter_map(|number| number.parse::<f64>().ok());
+        match (numbers.next(), numbers.next()) {
+            (Some(longitude), Some(latitude)) => lines.last_mut().unwrap().push((longitude, latitude)),
+            _ => lines.push(Vec::new()),
+        }
+    }
+    lines.retain(|line| line.len() > 1);
+    lines
+}
+
+/// Draw the path of a solar eclipse over an equirectangular map of the world, with the observer marked
+fn plot_eclipse_map(path: &EclipsePath, observer: &Observer, caption: &str, pixels: u32, output: &PathBuf) -> Result<(), String> {
+    let stroke_width_base = (pixels / 2048).max(1);
+    let root_drawing_area = BitMapBackend::new(output, (pixels, pixels / 2)).into_drawing_area();
+    root_drawing_area.fill(&BLACK).map_err(|e| e.to_string())?;
+    let mut chart = ChartBuilder::on(&root_drawing_area).margin(10).caption(caption, ("sans-serif", 20).into_font().color(&WHITE))
+        .build_cartesian_2d(-180.0..180.0, -90.0..90.0)
+        .map_err(|e| e.to_string())?;
+    let graticule = Into::<ShapeStyle>::into(GREY.mix(0.3)).stroke_width(stroke_width_base);
+    for longitude in (-180..=180).step_by(30) {
+        let longitude = longitude as f64;
+        chart.draw_series(LineSeries::new(vec![(longitude, -90.0), (longitude, 90.0)], graticule))
+            .map_err(|e| e.to_string())?;
+    }
+    for latitude in (-90..=90).step_by(30) {
+        let latitude = latitude as f64;
+        chart.draw_series(LineSeries::new(vec![(-180.0, latitude), (180.0, latitude)], graticule))
+            .map_err(|e| e.to_string())?;
+    }
+    let layers = [
+        (world_outline(), GREY, 1),
+        (path.penumbral_limits.clone(), YELLOW, 2),
+        (path.umbral_limits.clone(), RED, 2),
+        (path.centerline.clone(), WHITE, 2),
+    ];
+    for (lines, color, width) in layers {
+        for line in lines {
+            chart.draw_series(LineSeries::new(line, Into::<ShapeStyle>::into(color).stroke_width(stroke_width_base * width)))
+                .map_err(|e| e.to_string())?;
+        }
+    }
+    let home = (observer.longitude.get::<degree>(), observer.latitude.get::<degree>());
+    chart.draw_series(PointSeries::of_element(
+        vec![home],
+        stroke_width_base * 5,
+        Into::<ShapeStyle>::into(CYAN).filled(),
+        &|coord, size, style| EmptyElement::at(coord) + Circle::new((0, 0), size, style),
+    )).map_err(|e| e.to_string())?;
+    root_drawing_area.present().map_err(|e| e.to_string())?;
+    println!("Map written to {}", output.display());
+    Ok(())
+}
+
+fn print_next_sign_time(solar_system: &SolarSystem, planets: Vec<String>, sign: &String, start_time: Time, writer: TimeWriter) {
+    println!("Calculating next time for {sign} starting from {}:", writer.write(start_time));
     for planet in planets {
         // println!("Starting {planet}");
-        match solar_system.zodiac_for_dt(&planet, start_time) {
-            Some(s) => if s == sign.to_lowercase() {
-                    println!("  {planet}: already in {sign} at {start_time}");
+        match solar_system.zodiac_for(&planet, start_time) {
+            Some(s) => if s.eq_ignore_ascii_case(sign) {
+                    println!("  {planet}: already in {sign} at {}", writer.write(start_time));
                 } else {
                     // println!("{planet} starts in {s}");
-                    match solar_system.next_time_in_sign_dt(&planet, sign, start_time) {
-                        Some(st) => println!("  {planet}: {st}"),
+                    match solar_system.next_time_in_sign(&planet, sign, start_time) {
+                        Some(st) => println!("  {planet}: {}", writer.write(st)),
                         None => println!("Error: Could not get next time {planet} will be in {sign}"),
                     };
                 },
@@ -299,10 +517,10 @@ fn print_next_sign_time(solar_system: &SolarSystem, planets: Vec<String>, sign:
     }
 }
 
-fn print_current_signs(solar_system: &SolarSystem, planets: Vec<String>, time: OffsetDateTime) {
-    println!("Signs at {time}:");
+fn print_current_signs(solar_system: &SolarSystem, planets: Vec<String>, time: Time, writer: TimeWriter) {
+    println!("Signs at {}:", writer.write(time));
     for name in planets {
-        let sign = match solar_system.zodiac_for_dt(&name, time) {
+        let sign = match solar_system.zodiac_for(&name, time) {
             Some(s) => s,
             None => "Invalid".to_owned(),
         };
@@ -310,33 +528,433 @@ fn print_current_signs(solar_system: &SolarSystem, planets: Vec<String>, time: O
     }
 }
 
-#[derive(ValueEnum, Debug, Clone, Copy)]
-enum ZodiacObject {
-    Sun,
-    Mercury,
-    Venus,
-    Moon,
-    Mars,
-    Jupiter,
-    Saturn,
-    Uranus,
-    Neptune,
-}
-
-#[derive(Debug, Clone, Copy, ValueEnum)]
-enum ZodiacSign {
-    Aries,
-    Taurus,
-    Gemini,
-    Cancer,
-    Leo,
-    Virgo,
-    Libra,
-    Scorpio,
-    Sagittarius,
-    Capricorn,
-    Aquarius,
-    Pisces,
+fn print_coordinates<F: Frame>(solar_system: &SolarSystem, planets: Vec<String>, time: Time, equatorial: bool, writer: TimeWriter) {
+    println!("Coordinates at {} ({} {}, from {}):", writer.write(time),
+        format!("{:?}", solar_system.positions()).to_lowercase(), F::NAME, solar_system.zodiac_center().get_name());
+    for name in planets {
+        let Some(Spherical { longitude, latitude, distance }) = solar_system.coordinates_in::<F>(&name, time) else {
+            println!("  {name}: Invalid");
+            continue;
+        };
+        let longitude = if equatorial {
+            format!("RA {}", format_hms(longitude))
+        }
+        else {
+            format!("lon {:9.5}°", longitude.get::<degree>())
+        };
+        let latitude = format!("{} {}", if equatorial { "dec" } else { "lat" }, format_dms(latitude));
+        println!("  {name}: {longitude}  {latitude}  {:.6} AU", distance.get::<astronomical_unit>());
+    }
+}
+
+fn print_sky(solar_system: &SolarSystem, planets: Vec<String>, time: Time, refraction: bool, writer: TimeWriter) {
+    let observer = solar_system.observer();
+    println!("Sky at {} from {} {}, {:.0} m on {} ({}{}):", writer.write(time),
+        format_dms(observer.latitude), format_dms(observer.longitude), observer.elevation.get::<meter>(),
+        solar_system.zodiac_center().get_name(), format!("{:?}", solar_system.positions()).to_lowercase(),
+        if refraction { ", refracted" } else { "" });
+    for name in planets {
+        let (Some(horizontal), Some(equatorial)) = (
+            solar_system.horizontal_for(&name, time),
+            solar_system.topocentric_in::<EquatorialOfDate>(&name, time),
+        ) else {
+            println!("  {name}: Invalid");
+            continue;
+        };
+        let horizontal = if refraction { horizontal.refracted() } else { horizontal };
+        let Spherical { longitude, latitude, .. } = equatorial.spherical();
+        println!("  {name}: alt {}  az {:6.2}° {:<3}  RA {}  dec {}  {}",
+            format_dms(horizontal.altitude), horizontal.azimuth.get::<degree>(), horizontal.compass_point(),
+            format_hms(longitude), format_dms(latitude),
+            if horizontal.is_above_horizon() { "above horizon" } else { "below horizon" });
+    }
+}
+
+fn print_visibility(solar_system: &SolarSystem, planets: Vec<String>, time: Time, writer: TimeWriter) {
+    println!("Visibility at {} from {}:", writer.write(time), solar_system.zodiac_center().get_name());
+    for name in planets {
+        let Some(obj) = solar_system.get(&name).filter(|obj| !matches!(obj, SolarSystemObject::Calculated { .. })) else {
+            println!("  {name}: Invalid");
+            continue;
+        };
+        let visibility = photometry::visibility(solar_system, obj, time);
+        let mut parts = vec![match visibility.magnitude {
+            Some(magnitude) => format!("mag {magnitude:5.2}"),
+            None => "mag   ?  ".into(),
+        }];
+        if visibility.angular_diameter.get::<radian>() > 0.0 {
+            parts.push(format!("diameter {:8.2}\"", visibility.angular_diameter.get::<degree>() * 3600.0));
+        }
+        if let (Some(phase_angle), Some(illuminated)) = (visibility.phase_angle, visibility.illuminated) {
+            parts.push(format!("phase {:6.2}° ({:5.1}% lit)", phase_angle.get::<degree>(), illuminated * 100.0));
+        }
+        if let Some((elongation, east)) = visibility.elongation {
+            parts.push(format!("elongation {:6.2}° {}", elongation.get::<degree>(), if east { "E" } else { "W" }));
+        }
+        if !matches!(obj, SolarSystemObject::Star { .. }) {
+            parts.push(format!("{:.6} AU", visibility.distance.get::<astronomical_unit>()));
+        }
+        if let Some(horizontal) = solar_system.horizontal_for(&name, time) {
+            parts.push(format!("alt {}", format_dms(horizontal.refracted().altitude)));
+        }
+        println!("  {name}: {}", parts.join("  "));
+        if let Some(tilt) = visibility.ring_tilt {
+            println!("    rings tilted {:.2}° towards us, {} face", tilt.get::<degree>().abs(), if tilt.get::<radian>() >= 0.0 { "north" } else { "south" });
+        }
+    }
+}
+
+fn print_rise_set(solar_system: &SolarSystem, planets: Vec<String>, time: Time, days: u32, writer: TimeWriter) -> Result<(), String> {
+    let observer = solar_system.observer();
+    let starts = writer.days(time, days)?;
+    println!("Rise, transit and set from {} {}, times in {}:",
+        format_dms(observer.latitude), format_dms(observer.longitude), writer.zone.name());
+    for name in planets {
+        let Some(obj) = solar_system.get(&name) else {
+            println!("{name}: Invalid");
+            continue;
+        };
+        println!("{name}:");
+        for day in starts.windows(2) {
+            let date = CalendarDate::from_julian_date(timezone::wall(writer.zone, day[0]));
+            let events = riseset::rise_transit_set(solar_system, obj, day[0], day[1]);
+            let crossings = events.crossings;
+            let summary = if crossings.always_above() {
+                format!("always above the horizon{}", transit_summary(&events, writer))
+            }
+            else if crossings.always_below() {
+                "never rises".to_string()
+            }
+            else {
+                format!("rise {}{}  set {}", writer.write_clock_or_dashes(crossings.rise),
+                    transit_summary(&events, writer), writer.write_clock_or_dashes(crossings.set))
+            };
+            println!("  {:04}-{:02}-{:02}  {summary}", date.year, date.month, date.day);
+        }
+    }
+    Ok(())
+}
+
+fn print_moon(solar_system: &SolarSystem, time: Time, days: f64, writer: TimeWriter) -> Result<(), String> {
+    let state = lunar::moon_state(solar_system, time)?;
+    println!("Moon at {}:", writer.write(time));
+    println!("  {}, {:.1}% illuminated", state.phase_name(), state.illuminated * 100.0);
+    println!("  Elongation {:.2}°, phase angle {:.2}°", state.elongation.get::<degree>(), state.phase_angle.get::<degree>());
+    println!("  Age {:.2} days", state.age.get::<second>() / 86400.0);
+    let end = time + Time::new::<second>(days * 86400.0);
+    println!("Phases until {}:", writer.write(end));
+    for (phase, time) in lunar::phases_between(solar_system, time, end)? {
+        println!("  {:<15}{}", phase.name(), writer.write(time));
+    }
+    Ok(())
+}
+
+fn print_lagrange(solar_system: &SolarSystem, planets: Vec<String>, time: Time, writer: TimeWriter) {
+    println!("Lagrange points at {}:", writer.write(time));
+    for name in planets {
+        let Some(body) = solar_system.get_rc(&name) else {
+            println!("  {name}: Invalid");
+            continue;
+        };
+        let Some(orbit) = body.orbitor() else {
+            println!("  {name}: not on an orbit");
+            continue;
+        };
+        if let Err(e) = LagrangePoint::new(body.clone(), Lagrange::L1) {
+            println!("  {e}");
+            continue;
+        }
+        let parent = orbit.parent();
+        println!("  {name} around {}: at mean distance, Hill sphere {:.6} AU, sphere of influence {:.6} AU", parent.get_name(),
+            orbit.hill_radius(time).get::<astronomical_unit>(), orbit.sphere_of_influence(time).get::<astronomical_unit>());
+        for point in Lagrange::ALL {
+            let Ok(point_at) = LagrangePoint::new(body.clone(), point) else {
+                continue;
+            };
+            let position = point_at.position(time);
+            let from = |obj: &SolarSystemObject| (position - obj.position(time)).norm().get::<astronomical_unit>();
+            println!("    {}  {:.6} AU from {name}  {:.6} AU from {}", point.name(), from(&body), from(parent), parent.get_name());
+        }
+    }
+}
+
+fn print_seasons(solar_system: &SolarSystem, year: i32, years: u32, writer: TimeWriter) -> Result<(), String> {
+    let last = year + years.max(1) as i32 - 1;
+    let seasons = seasons::seasons_between(solar_system, year, last)?;
+    // The last season runs to the next year's March equinox
+    let after = seasons::season_in(solar_system, seasons::Season::MarchEquinox, last + 1)?;
+    let northern = solar_system.observer().latitude.get::<degree>() >= 0.0;
+    println!("Equinoxes and solstices from {year} to {last} ({} hemisphere seasons):", if northern { "northern" } else { "southern" });
+    for (i, &(season, time)) in seasons.iter().enumerate() {
+        let next = seasons.get(i + 1).map_or(after, |&(_, time)| time);
+        let length = (next - time).get::<second>() / 86400.0;
+        println!("  {}  {:<18}{} begins, lasting {length:.2} days", writer.write(time), season.name(), season.begins(northern));
+    }
+    Ok(())
+}
+
+fn print_eclipses(solar_system: &SolarSystem, time: Time, days: f64, writer: TimeWriter) -> Result<(), String> {
+    let end = time + Time::new::<second>(days * 86400.0);
+    let eclipses = eclipse::eclipses_between(solar_system, time, end)?;
+    println!("Eclipses from {} to {}:", writer.write(time), writer.write(end));
+    if eclipses.is_empty() {
+        println!("  none");
+    }
+    for eclipse in eclipses {
+        let kind = match eclipse.kind {
+            EclipseKind::Solar => "solar",
+            EclipseKind::Lunar => "lunar",
+        };
+        let central = match (eclipse.kind, eclipse.eclipse_type, eclipse.central) {
+            (EclipseKind::Solar, EclipseType::Total | EclipseType::Annular, false) => " (non-central)",
+            _ => "",
+        };
+        println!("{} {kind} eclipse{central}, greatest at {}", eclipse.eclipse_type.name(), writer.write(eclipse.greatest));
+        match eclipse.penumbral_magnitude {
+            Some(penumbral) => println!("  Umbral magnitude {:.4}, penumbral magnitude {:.4}, gamma {:+.4}", eclipse.magnitude, penumbral, eclipse.gamma),
+            None => println!("  Magnitude {:.4}, gamma {:+.4}", eclipse.magnitude, eclipse.gamma),
+        }
+        let contacts: Vec<String> = eclipse.contacts.list().into_iter()
+            .map(|(name, time)| format!("{name} {}", writer.write_clock(time)))
+            .collect();
+        println!("  {}", contacts.join("  "));
+    }
+    Ok(())
+}
+
+fn print_events(solar_system: &SolarSystem, planets: Vec<String>, time: Time, days: f64, within: f64, writer: TimeWriter) -> Result<(), String> {
+    let end = time + Time::new::<second>(days * 86400.0);
+    let events = events::events_between(solar_system, &planets, time, end, Angle::new::<degree>(within))?;
+    println!("Events from {} to {}:", writer.write(time), writer.write(end));
+    if events.is_empty() {
+        println!("  none");
+    }
+    for event in events {
+        let detail = match &event.kind {
+            EventKind::GreatestElongation { elongation, .. } => format!(" ({:.2}°)", elongation.get::<degree>()),
+            EventKind::Periapsis { parent, distance } | EventKind::Apoapsis { parent, distance } => {
+                if *parent == solar_system.zodiac_center().get_name() {
+                    format!(" ({:.0} km)", distance.get::<meter>() / 1000.0)
+                }
+                else {
+                    format!(" ({:.6} AU)", distance.get::<astronomical_unit>())
+                }
+            },
+            EventKind::ClosestApproach { separation, .. } => format!(" ({:.2}°)", separation.get::<degree>()),
+            EventKind::Conjunction | EventKind::InferiorConjunction | EventKind::SuperiorConjunction | EventKind::Opposition => String::new(),
+        };
+        println!("  {}  {} {}{detail}", writer.write(event.time), event.body, event.name());
+    }
+    Ok(())
+}
+
+fn print_alignments(solar_system: &SolarSystem, planets: Vec<String>, time: Time, search: &AlignArgs, writer: TimeWriter) {
+    let AlignArgs { years, span, orb, top } = *search;
+    let end = time + Time::new::<second>(years * 365.25 * 86400.0);
+    let bodies: Vec<&SolarSystemObject> = planets.iter().filter_map(|name| solar_system.get(name)).collect();
+    let window = |window: &alignment::Window| format!("at {}, from {} to {}",
+        writer.write(window.tightest), writer.write(window.start), writer.write(window.end));
+    let clusters = alignment::clusters_between(solar_system, &bodies, Angle::new::<degree>(span), time, end);
+    println!("{} within {span}° from {} to {}, tightest first:", planets.join(", "), writer.write(time), writer.write(end));
+    if clusters.is_empty() {
+        println!("  none");
+    }
+    for cluster in clusters.iter().take(top) {
+        println!("  {:6.2}° {} to {}  {}", cluster.window.value.get::<degree>(), cluster.first, cluster.last, window(&cluster.window));
+    }
+    let Some(orb) = orb else {
+        return;
+    };
+    let configurations = alignment::configurations_between(solar_system, &bodies, Angle::new::<degree>(orb), time, end);
+    println!("Aspect patterns within {orb}° of exact, tightest first:");
+    if configurations.is_empty() {
+        println!("  none");
+    }
+    for configuration in configurations.iter().take(top) {
+        println!("  {:5.2}° {} of {}  {}", configuration.window.value.get::<degree>(), configuration.pattern.name(),
+            configuration.bodies.join(", "), window(&configuration.window));
+    }
+}
+
+fn print_occultations(solar_system: &SolarSystem, time: Time, days: f64, geocentric: bool, writer: TimeWriter) -> Result<(), String> {
+    let end = time + Time::new::<second>(days * 86400.0);
+    let mut passages = occultation::transits_between(solar_system, time, end, !geocentric)?;
+    passages.extend(occultation::occultations_between(solar_system, time, end, !geocentric)?);
+    passages.sort_by(|a, b| a.greatest.partial_cmp(&b.greatest).unwrap());
+    let observer = solar_system.observer();
+    let from = if geocentric {
+        format!("the center of {}", solar_system.zodiac_center().get_name())
+    }
+    else {
+        format!("{} {}", format_dms(observer.latitude), format_dms(observer.longitude))
+    };
+    println!("Transits and occultations seen from {from}, {} to {}:", writer.write(time), writer.write(end));
+    if passages.is_empty() {
+        println!("  none");
+    }
+    for passage in passages {
+        let what = match passage.kind {
+            PassageKind::Transit => format!("Transit of {}", passage.front),
+            PassageKind::Occultation => format!("Occultation of {} by the {}", passage.behind, passage.front),
+        };
+        let grazing = if passage.grazing { " (grazing)" } else { "" };
+        println!("{what}{grazing}, greatest at {}", writer.write(passage.greatest));
+        let mut details = format!("  Least separation {:.2}'", passage.separation.get::<degree>() * 60.0);
+        if !geocentric {
+            if let Some(horizontal) = solar_system.horizontal_for(&passage.behind, passage.greatest) {
+                let horizon = if horizontal.is_above_horizon() { "" } else { ", below the horizon" };
+                details += &format!(", {} altitude {}{horizon}", passage.behind, format_dms(horizontal.altitude));
+            }
+        }
+        println!("{details}");
+        let contacts: Vec<String> = passage.contacts().into_iter()
+            .map(|(name, time)| format!("{name} {}", writer.write_clock(time)))
+            .collect();
+        println!("  {}", contacts.join("  "));
+    }
+    Ok(())
+}
+
+fn print_eclipse(solar_system: &SolarSystem, time: Time, map: Option<PathBuf>, pixels: u32, writer: TimeWriter) -> Result<(), String> {
+    // Solar eclipses come at least twice a year, so the next one is within a year and a bit
+    let end = time + Time::new::<second>(400.0 * 86400.0);
+    let eclipse = eclipse::eclipses_between(solar_system, time, end)?.into_iter()
+        .find(|eclipse| eclipse.kind == EclipseKind::Solar)
+        .ok_or("No solar eclipse found")?;
+    let central = if eclipse.central || eclipse.eclipse_type == EclipseType::Partial { "" } else { " (non-central)" };
+    println!("{} solar eclipse{central}, greatest at {}", eclipse.eclipse_type.name(), writer.write(eclipse.greatest));
+    println!("  Magnitude {:.4}, gamma {:+.4}", eclipse.magnitude, eclipse.gamma);
+    let observer = solar_system.observer();
+    println!("From {} {}:", format_dms(observer.latitude), format_dms(observer.longitude));
+    match eclipse::local_circumstances(solar_system, &eclipse)? {
+        None => println!("  not visible"),
+        Some(local) => {
+            let sun_altitude = |time: Time| solar_system.horizontal_for("Sun", time)
+                .map_or("Invalid".to_string(), |horizontal| format_dms(horizontal.altitude));
+            println!("  {} eclipse, maximum at {}", local.eclipse_type.name(), writer.write(local.maximum));
+            println!("  Magnitude {:.4}, obscuration {:.1}%", local.magnitude, local.obscuration * 100.0);
+            let mut rows = local.contacts();
+            rows.push(("Max", local.maximum));
+            rows.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+            for (name, time) in rows {
+                println!("  {name:<4}{}  Sun altitude {}", writer.write_clock(time), sun_altitude(time));
+            }
+            if solar_system.horizontal_for("Sun", local.maximum).is_some_and(|horizontal| !horizontal.is_above_horizon()) {
+                println!("  The Sun is below the horizon at maximum");
+            }
+        },
+    }
+    if let Some(output) = map {
+        let path = eclipse::eclipse_path(solar_system, &eclipse)?;
+        let date = CalendarDate::from_julian_date(tt_to_utc_jd(eclipse.greatest));
+        let caption = format!("{} solar eclipse of {:04}-{:02}-{:02}", eclipse.eclipse_type.name(), date.year, date.month, date.day);
+        plot_eclipse_map(&path, observer, &caption, pixels, &output)?;
+    }
+    Ok(())
+}
+
+fn print_almanac(solar_system: &SolarSystem, time: Time, days: u32, format: AlmanacFormat, output: Option<PathBuf>, writer: TimeWriter)
+        -> Result<(), String> {
+    let sun = solar_system.get("Sun").ok_or("The almanac needs an object named Sun")?;
+    let observer = solar_system.observer();
+    let mut lines = Vec::new();
+    match format {
+        AlmanacFormat::Table => {
+            lines.push(format!("Sun almanac for {} {}, times in {}:",
+                format_dms(observer.latitude), format_dms(observer.longitude), writer.zone.name()));
+            lines.push(format!("{:<12}{:<10}{:<10}{:<10}{:<10}{:<20}{:<10}{:<10}{:<10}{:<10}{}",
+                "Date", "Astro", "Nautical", "Civil", "Sunrise", "Solar noon", "Sunset", "Civil", "Nautical", "Astro", "Day length"));
+        },
+        AlmanacFormat::Csv => lines.push("date,astronomical_dawn,nautical_dawn,civil_dawn,sunrise,solar_noon,noon_altitude,\
+            equation_of_time,declination,sunset,\
+            civil_dusk,nautical_dusk,astronomical_dusk,day_length,morning_golden_hour_start,morning_golden_hour_end,\
+            evening_golden_hour_start,evening_golden_hour_end,morning_blue_hour_start,morning_blue_hour_end,\
+            evening_blue_hour_start,evening_blue_hour_end,note".to_string()),
+    }
+    for day in writer.days(time, days)?.windows(2) {
+        let date = CalendarDate::from_julian_date(timezone::wall(writer.zone, day[0]));
+        let date = format!("{:04}-{:02}-{:02}", date.year, date.month, date.day);
+        let almanac = SunAlmanac::new(solar_system, sun, day[0], day[1]);
+        let length = almanac.day_length.get::<second>().round() as u32;
+        let length = format!("{:02}:{:02}:{:02}", length / 3600, length / 60 % 60, length % 60);
+        let note = almanac.note().unwrap_or("");
+        match format {
+            AlmanacFormat::Table => {
+                let clock = |time: Option<Time>| writer.write_clock_or_dashes(time);
+                let noon = almanac.solar_noon.map_or("--:--:--".to_string(),
+                    |(time, altitude)| format!("{} {:+5.1}°", writer.write_clock(time), altitude.get::<degree>()));
+                lines.push(format!("{date:<12}{:<10}{:<10}{:<10}{:<10}{noon:<20}{:<10}{:<10}{:<10}{:<10}{length}  {note}",
+                    clock(almanac.astronomical.rise), clock(almanac.nautical.rise), clock(almanac.civil.rise),
+                    clock(almanac.sunrise.rise), clock(almanac.sunrise.set),
+                    clock(almanac.civil.set), clock(almanac.nautical.set), clock(almanac.astronomical.set)).trim_end().to_string());
+                let range = |from: Option<Time>, to: Option<Time>| match (from, to) {
+                    (None, None) => "none".to_string(),
+                    _ => format!("{}–{}", clock(from), clock(to)),
+                };
+                let equation = almanac.equation_of_time.get::<second>();
+                lines.push(format!("{:<12}golden hour {} and {}, blue hour {} and {}, equation of time {}{}m{:02}s, declination {}", "",
+                    range(almanac.blue_hour.rise, almanac.golden_hour.rise), range(almanac.golden_hour.set, almanac.blue_hour.set),
+                    range(almanac.civil.rise, almanac.blue_hour.rise), range(almanac.blue_hour.set, almanac.civil.set),
+                    if equation < 0.0 { "-" } else { "+" }, equation.abs().round() as u32 / 60, equation.abs().round() as u32 % 60,
+                    format_dms(almanac.declination)));
+            },
+            AlmanacFormat::Csv => {
+                let clock = |time: Option<Time>| time.map_or(String::new(), |time| writer.write_clock(time));
+                let (noon, altitude) = almanac.solar_noon.map_or((String::new(), String::new()),
+                    |(time, altitude)| (writer.write_clock(time), format!("{:.2}", altitude.get::<degree>())));
+                lines.push([
+                    date, clock(almanac.astronomical.rise), clock(almanac.nautical.rise), clock(almanac.civil.rise),
+                    clock(almanac.sunrise.rise), noon, altitude,
+                    format!("{:.2}", almanac.equation_of_time.get::<second>() / 60.0), format!("{:.4}", almanac.declination.get::<degree>()),
+                    clock(almanac.sunrise.set),
+                    clock(almanac.civil.set), clock(almanac.nautical.set), clock(almanac.astronomical.set), length,
+                    clock(almanac.blue_hour.rise), clock(almanac.golden_hour.rise), clock(almanac.golden_hour.set), clock(almanac.blue_hour.set),
+                    clock(almanac.civil.rise), clock(almanac.blue_hour.rise), clock(almanac.blue_hour.set), clock(almanac.civil.set),
+                    note.to_string(),
+                ].join(","));
+            },
+        }
+    }
+    let contents = lines.join("\n") + "\n";
+    match output {
+        Some(path) => std::fs::write(&path, contents).map_err(|e| format!("Could not write {}: {e}", path.display())),
+        None => {
+            print!("{contents}");
+            Ok(())
+        },
+    }
+}
+
+fn transit_summary(events: &RiseTransitSet, writer: TimeWriter) -> String {
+    match events.transit {
+        Some((time, altitude)) => format!("  transit {} at {}", writer.write_clock(time), format_dms(altitude)),
+        None => "  transit --:--:--".to_string(),
+    }
+}
+
+fn print_time_scales(time: Time, writer: TimeWriter) {
+    for scale in TimeScale::ALL {
+        let jd = scale.julian_date(time);
+        let date = CalendarDate::from_julian_date(jd);
+        let calendar = if date.calendar() == Calendar::Julian { " (Julian)" } else { "" };
+        println!("  {:<4}{date:.3}{calendar}  JD{jd:.8}  MJD{:.8}", format!("{scale:?}").to_uppercase(), jd - MJD_OFFSET);
+    }
+    if writer.zone != timezone::utc() {
+        println!("  {}  {}", writer.zone.name(), TimeWriter { format: TimeFormat::Calendar, ..writer }.write(time));
+    }
+    println!("  ΔT  {:.3} s", timescales::delta_t(time).get::<second>());
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum CoordinateFrame {
+    /// Mean ecliptic and equinox of J2000
+    EclipticJ2000,
+    /// Ecliptic and true equinox of date
+    EclipticOfDate,
+    /// Mean equator and equinox of J2000 (ICRF)
+    EquatorialJ2000,
+    /// True equator and equinox of date
+    EquatorialOfDate,
 }
 
 #[derive(Clone, Copy, Debug, ValueEnum)]
@@ -345,6 +963,8 @@ enum PlotMode {
     Rel2d,
     Abs3d,
     Rel3d,
+    /// The Sun in the observer's sky at the same clock time every day for a year
+    Analemma,
 }
 
 #[derive(Subcommand, Debug, Clone)]
@@ -355,64 +975,485 @@ enum Command {
         // objects: Option<Vec<PredefinedObject>>,
         #[arg(short, long, default_value="2048")]
         pixels: u32,
-        #[arg(short, long, default_value="200.0")]
+        #[arg(short, long, default_value="300.0")]
         scale: f64,
-        #[arg(short, long, default_value="now", value_parser=parse_time)]
-        time: OffsetDateTime,
+        #[arg(short, long, default_value="now", value_parser=parse_time, allow_hyphen_values=true)]
+        time: TimeInput,
         #[arg(short, long, default_value="abs2d")]
         mode: PlotMode,
     },
     /// Calculate zodiac signs for planets at a point in time 
     Sign { 
+        /// Objects to include, e.g. sun,moon,mean-node (default: everything but the zodiac center)
         #[arg(short, long, value_delimiter=',', num_args=1..)]
-        planets: Option<Vec<ZodiacObject>>,
-        #[arg(short, long, default_value="now", value_parser=parse_time)]
-        time: OffsetDateTime,
+        planets: Option<Vec<String>>,
+        #[arg(short, long, default_value="now", value_parser=parse_time, allow_hyphen_values=true)]
+        time: TimeInput,
     },
     /// Calculate next time planets will enter a zodiac sign from a starting time
     Next { 
-        sign: ZodiacSign,
+        /// Zodiac sign, e.g. aries
+        sign: String,
+        /// Objects to include, e.g. sun,moon,mean-node (default: everything but the zodiac center)
+        #[arg(short, long, value_delimiter=',', num_args=1..)]
+        planets: Option<Vec<String>>,
+        #[arg(short, long, default_value="now", value_parser=parse_time, allow_hyphen_values=true)]
+        time: TimeInput,
+    },
+    /// Show right ascension and declination, or ecliptic coordinates, of objects seen from the zodiac center
+    Coords {
+        /// Objects to include, e.g. sun,moon,mean-node (default: everything but the zodiac center)
+        #[arg(short, long, value_delimiter=',', num_args=1..)]
+        planets: Option<Vec<String>>,
+        #[arg(short, long, default_value="now", value_parser=parse_time, allow_hyphen_values=true)]
+        time: TimeInput,
+        #[arg(short, long, default_value="equatorial-of-date")]
+        frame: CoordinateFrame,
+    },
+    /// Show where objects are in the observer's sky: altitude, azimuth, and topocentric RA and declination
+    Sky {
+        /// Objects to include, e.g. sun,moon,mean-node (default: everything but the zodiac center)
+        #[arg(short, long, value_delimiter=',', num_args=1..)]
+        planets: Option<Vec<String>>,
+        #[arg(short, long, default_value="now", value_parser=parse_time, allow_hyphen_values=true)]
+        time: TimeInput,
+        /// Raise altitudes by atmospheric refraction, to where objects appear
+        #[arg(short, long)]
+        refraction: bool,
+    },
+    /// Show how bright and how big objects look: magnitude, angular diameter, phase, elongation from the Sun, distance and
+    /// altitude for the observer, and the tilt of Saturn's rings
+    Visibility {
+        /// Objects to include, e.g. venus,mars,saturn (default: everything but the zodiac center)
+        #[arg(short, long, value_delimiter=',', num_args=1..)]
+        planets: Option<Vec<String>>,
+        #[arg(short, long, default_value="now", value_parser=parse_time, allow_hyphen_values=true)]
+        time: TimeInput,
+    },
+    /// Find when objects rise, cross the meridian and set for the observer, day by day
+    Riseset {
+        /// Objects to include, e.g. sun,moon,mean-node (default: everything but the zodiac center)
+        #[arg(short, long, value_delimiter=',', num_args=1..)]
+        planets: Option<Vec<String>>,
+        /// Any time on the first day, which is a day in the time zone set by --tz
+        #[arg(short, long, default_value="now", value_parser=parse_time, allow_hyphen_values=true)]
+        time: TimeInput,
+        /// Number of days to cover
+        #[arg(short, long, default_value="1")]
+        days: u32,
+    },
+    /// Print the Sun's daily events for the observer: twilights, sunrise and sunset, solar noon, day length, and golden and blue hours
+    Almanac {
+        /// Any time on the first day, which is a day in the time zone set by --tz
+        #[arg(short, long, default_value="now", value_parser=parse_time, allow_hyphen_values=true)]
+        time: TimeInput,
+        /// Number of days to cover
+        #[arg(short, long, default_value="7")]
+        days: u32,
+        #[arg(short, long, default_value="table")]
+        format: AlmanacFormat,
+        /// File to write; prints to the terminal if left out
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Show the Moon's phase, illumination and age, and list the times of its principal phases
+    Moon {
+        #[arg(short, long, default_value="now", value_parser=parse_time, allow_hyphen_values=true)]
+        time: TimeInput,
+        /// Number of days after the time to list phases for
+        #[arg(short, long, default_value="30")]
+        days: f64,
+    },
+    /// Show the Hill sphere and sphere of influence of bodies on orbits at their mean distances, and where their Lagrange points with the body
+    /// they orbit are (add the points as objects with --lagrange)
+    Lagrange {
+        /// Objects to include, e.g. earth,jupiter (default: everything on an orbit)
+        #[arg(short, long, value_delimiter=',', num_args=1..)]
+        planets: Option<Vec<String>>,
+        #[arg(short, long, default_value="now", value_parser=parse_time, allow_hyphen_values=true)]
+        time: TimeInput,
+    },
+    /// Find the times of the equinoxes and solstices, when the Sun's apparent longitude is a multiple of 90°, and the
+    /// lengths of the seasons they begin
+    Seasons {
+        /// First year; this year if left out
+        #[arg(short, long, allow_hyphen_values=true)]
+        year: Option<i32>,
+        /// Number of years to cover
+        #[arg(short = 'n', long, default_value="1")]
+        years: u32,
+    },
+    /// Find solar and lunar eclipses, with their type, greatest eclipse, magnitude, gamma and contact times
+    Eclipses {
+        /// Start of the search
+        #[arg(short, long, default_value="now", value_parser=parse_time, allow_hyphen_values=true)]
+        time: TimeInput,
+        /// Number of days to search
+        #[arg(short, long, default_value="365")]
+        days: f64,
+    },
+    /// Find transits of the inner planets across the Sun and occultations of planets and stars by the Moon, with
+    /// their contact times and least separation (add stars with --stars or a system file)
+    Occultations {
+        /// Start of the search
+        #[arg(short, long, default_value="now", value_parser=parse_time, allow_hyphen_values=true)]
+        time: TimeInput,
+        /// Number of days to search
+        #[arg(short, long, default_value="365")]
+        days: f64,
+        /// Look from the center of the Earth instead of the observer
+        #[arg(short, long)]
+        geocentric: bool,
+    },
+    /// List conjunctions and oppositions with the Sun, greatest elongations, perihelia and aphelia, perigees and apogees,
+    /// and close approaches between bodies, in order
+    Events {
+        /// Objects to include, e.g. sun,moon,mars (default: everything)
         #[arg(short, long, value_delimiter=',', num_args=1..)]
-        planets: Option<Vec<ZodiacObject>>,
-        #[arg(short, long, default_value="now", value_parser=parse_time)]
-        time: OffsetDateTime,
+        planets: Option<Vec<String>>,
+        /// Start of the search
+        #[arg(short, long, default_value="now", value_parser=parse_time, allow_hyphen_values=true)]
+        time: TimeInput,
+        /// Number of days to search
+        #[arg(short, long, default_value="365")]
+        days: f64,
+        /// Only list approaches closer than this many degrees
+        #[arg(short, long, default_value="5")]
+        within: f64,
+    },
+    /// Find when bodies gather within a span of longitude, tightest first, and optionally grand trines, T-squares and
+    /// grand crosses among them
+    Align {
+        /// Objects to include, e.g. sun,moon,mars
+        #[arg(short, long, value_delimiter=',', num_args=1.., default_value="mercury,venus,mars,jupiter,saturn")]
+        planets: Vec<String>,
+        /// Start of the search
+        #[arg(short, long, default_value="now", value_parser=parse_time, allow_hyphen_values=true)]
+        time: TimeInput,
+        #[command(flatten)]
+        search: AlignArgs,
+    },
+    /// Show what the observer sees of the next solar eclipse: contacts, magnitude, obscuration and the Sun's altitude
+    Eclipse {
+        /// Start of the search for the eclipse
+        #[arg(short, long, default_value="now", value_parser=parse_time, allow_hyphen_values=true)]
+        time: TimeInput,
+        /// Also draw a map of the eclipse's path to this PNG file
+        #[arg(short, long)]
+        map: Option<PathBuf>,
+        /// Width of the map; it is half as tall
+        #[arg(short, long, default_value="2048")]
+        pixels: u32,
+    },
+    /// Show a time in each time scale, and ΔT
+    Time {
+        #[arg(short, long, default_value="now", value_parser=parse_time, allow_hyphen_values=true)]
+        time: TimeInput,
+    },
+    /// Write the system (the default one, or the one given with --system) as TOML or JSON
+    Dump {
+        /// File to write; prints to the terminal if left out
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Defaults to JSON for .json output files and TOML otherwise
+        #[arg(short, long)]
+        format: Option<DumpFormat>,
     },
 }
 
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum DumpFormat {
+    Toml,
+    Json,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum AlmanacFormat {
+    Table,
+    Csv,
+}
+
+/// Minor bodies to import from Minor Planet Center orbit files
+#[derive(clap::Args, Debug, Clone)]
+struct BodiesArgs {
+    /// MPCORB or CometEls file of asteroids and comets to add to the system
+    #[arg(long, global=true)]
+    bodies_file: Vec<PathBuf>,
+    #[arg(long, global=true, default_value="auto")]
+    bodies_format: MpcFormat,
+    /// Only import bodies whose name or designation contains one of these
+    #[arg(long, global=true, value_delimiter=',')]
+    bodies_name: Vec<String>,
+    /// Only import bodies with these minor planet (or periodic comet) numbers
+    #[arg(long, global=true, value_delimiter=',')]
+    bodies_number: Vec<u32>,
+    /// Only import bodies with an absolute magnitude H at most this
+    #[arg(long, global=true)]
+    max_magnitude: Option<f64>,
+    /// Import at most this many bodies per file
+    #[arg(long, global=true)]
+    bodies_limit: Option<usize>,
+}
+
+impl BodiesArgs {
+    fn filter(&self) -> MpcFilter {
+        MpcFilter {
+            names: self.bodies_name.clone(),
+            numbers: self.bodies_number.clone(),
+            max_magnitude: self.max_magnitude,
+            limit: self.bodies_limit,
+        }
+    }
+
+    fn load_into(&self, solar_system: &mut SolarSystem) -> Result<(), String> {
+        if self.bodies_file.is_empty() {
+            return Ok(());
+        }
+        let sun = solar_system.get_rc("Sun").ok_or("Minor bodies need a Sun to orbit")?;
+        let filter = self.filter();
+        for path in &self.bodies_file {
+            let import = mpc::read_file(path, self.bodies_format, &filter)?;
+            let found = import.records.len();
+            let added = solar_system.extend(import.records.into_iter().map(|r| r.into_object(sun.clone())));
+            // Kept off stdout, which may be CSV or JSON for another program
+            eprintln!("Loaded {added} of {found} bodies from {} ({} records skipped)", path.display(), import.skipped);
+        }
+        Ok(())
+    }
+}
+
+/// How long and how loosely the align command searches, and how much it lists
+#[derive(clap::Args, Debug, Clone)]
+struct AlignArgs {
+    /// Number of years to search
+    #[arg(short, long, default_value="10")]
+    years: f64,
+    /// Largest span of longitude, in degrees, that the bodies must all fit in
+    #[arg(short, long, default_value="30")]
+    span: f64,
+    /// Also look for aspect patterns, within this many degrees of exact
+    #[arg(short, long)]
+    orb: Option<f64>,
+    /// Number of results to list of each kind
+    #[arg(short = 'n', long, default_value="20")]
+    top: usize,
+}
+
 ///Keplerian simulation of the solar system. Supports 2D and 3D plots and zodiac sign calculations.
 #[derive(Parser, Debug)]
 #[command(version, about, long_about=None)]
 struct Args {
     #[command(subcommand)]
     command: Command,
+    /// Observer latitude in degrees, north positive (used by `sky` and chart points like the Part of Fortune)
+    #[arg(long, global=true, default_value="51.4769", allow_negative_numbers=true)]
+    latitude: f64,
+    /// Observer longitude in degrees, east positive
+    #[arg(long, global=true, default_value="0.0", allow_negative_numbers=true)]
+    longitude: f64,
+    /// Observer elevation in meters above the WGS84 ellipsoid
+    #[arg(long, global=true, default_value="46.0", allow_negative_numbers=true)]
+    elevation: f64,
+    /// Equinox that zodiac signs are measured from
+    #[arg(long, global=true, default_value="date")]
+    equinox: Equinox,
+    /// Correct positions for light travel time (astrometric) and also aberration (apparent)
+    #[arg(long, global=true, default_value="apparent")]
+    positions: Positions,
+    /// Include the bending of light by the Sun in apparent positions
+    #[arg(long, global=true)]
+    deflection: bool,
+    /// How to write times: calendar dates (Julian before 1582-10-15), Julian dates or modified Julian dates
+    #[arg(long, global=true, default_value="calendar")]
+    time_format: TimeFormat,
+    /// IANA time zone to read and write dates in, e.g. Europe/London (default: UTC)
+    #[arg(long, global=true, value_parser=timezone::zone_by_name)]
+    tz: Option<&'static Tz>,
+    /// Read and write dates in this computer's time zone
+    #[arg(long, global=true, conflicts_with="tz")]
+    local: bool,
+    /// File of ΔT (TT - UT1) values, one "year seconds" pair per line, to use instead of the built-in model
+    #[arg(long, global=true)]
+    delta_t_file: Option<PathBuf>,
+    /// TOML or JSON file describing the system to simulate instead of the default solar system
+    #[arg(long, global=true)]
+    system: Option<PathBuf>,
+    /// Add the bright stars the Moon can pass in front of: Alcyone, Aldebaran, Regulus, Spica and Antares
+    #[arg(long, global=true)]
+    stars: bool,
+    /// Add the L1 to L5 points of these bodies and the bodies they orbit, e.g. jupiter,earth; `plot` shows the Trojan
+    /// regions around L4 and L5
+    #[arg(long, global=true, value_delimiter=',')]
+    lagrange: Vec<String>,
+    #[command(flatten)]
+    bodies: BodiesArgs,
+}
+
+/// Map object names given on the command line to the system's own names, defaulting to everything but the zodiac center
+fn resolve_objects(solar_system: &SolarSystem, planets: Option<Vec<String>>) -> Result<Vec<String>, String> {
+    match planets {
+        Some(pl) => pl.iter()
+            .map(|name| solar_system.get(name).map(|obj| obj.get_name())
+                .ok_or(format!("Unknown object {name}, expected one of: {}", solar_system.names().join(", "))))
+            .collect(),
+        None => Ok(solar_system.names().into_iter().filter(|s| *s != solar_system.zodiac_center().get_name()).collect()),
+    }
+}
+
+fn resolve_sign(solar_system: &SolarSystem, sign: &str) -> Result<String, String> {
+    let signs = solar_system.zodiac().signs();
+    signs.iter()
+        .find(|s| s.eq_ignore_ascii_case(sign))
+        .cloned()
+        .ok_or(format!("Unknown sign {sign}, expected one of: {}", signs.join(", ")))
+}
+
+fn dump_system(solar_system: &SolarSystem, output: Option<PathBuf>, format: Option<DumpFormat>) -> Result<(), String> {
+    let format = match (format, &output) {
+        (Some(DumpFormat::Toml), _) => ConfigFormat::Toml,
+        (Some(DumpFormat::Json), _) => ConfigFormat::Json,
+        (None, Some(path)) => ConfigFormat::from_path(path),
+        (None, None) => ConfigFormat::Toml,
+    };
+    let contents = SystemConfig::from_system(solar_system).to_string(format)?;
+    match output {
+        Some(path) => std::fs::write(&path, contents).map_err(|e| format!("Could not write {}: {e}", path.display())),
+        None => {
+            print!("{contents}");
+            Ok(())
+        },
+    }
 }
 
 fn main() {
-    let solar_system = SolarSystem::new_default();
     let args = Args::parse();
+    if let Err(e) = run(args) {
+        eprintln!("Error: {e}");
+        std::process::exit(1);
+    }
+}
+
+fn run(args: Args) -> Result<(), String> {
+    if let Some(path) = &args.delta_t_file {
+        timescales::load_delta_t_table(path)?;
+    }
+    let observer = Observer::new::<degree>(args.latitude, args.longitude, args.elevation);
+    let mut solar_system = match &args.system {
+        Some(path) => config::load_system(path, observer)?,
+        None => SolarSystem::new_default_at(observer),
+    }.with_equinox(args.equinox)
+    .with_positions(args.positions)
+    .with_deflection(args.deflection)
+    .with_observer(observer);
+    if args.stars {
+        solar_system.extend(stars::bright_stars());
+    }
+    args.bodies.load_into(&mut solar_system)?;
+    for name in &args.lagrange {
+        let points = lagrange::lagrange_points(&solar_system, name)?;
+        solar_system.extend(points);
+    }
+    let zone = match (args.tz, args.local) {
+        (Some(zone), _) => zone,
+        (None, true) => timezone::local_zone()?,
+        (None, false) => timezone::utc(),
+    };
+    let writer = TimeWriter { format: args.time_format, zone };
     match args.command {
         Command::Plot { pixels, scale, time, mode, } => {
-            let start_time = dt_to_internal(time);
+            let time = time.resolve(zone)?;
             match mode {
-                PlotMode::Abs2d => plot_2d(&solar_system, pixels, scale, start_time),
-                PlotMode::Rel2d => plot_rel_2d(&solar_system, pixels, scale, start_time),
-                PlotMode::Abs3d => plot_3d(&solar_system, pixels, scale, start_time),
-                PlotMode::Rel3d => plot_rel_3d(&solar_system, pixels, scale, start_time),
+                PlotMode::Abs2d => plot_2d(&solar_system, pixels, scale, time),
+                PlotMode::Rel2d => plot_rel_2d(&solar_system, pixels, scale, time)?,
+                PlotMode::Abs3d => plot_3d(&solar_system, pixels, scale, time),
+                PlotMode::Rel3d => plot_rel_3d(&solar_system, pixels, scale, time)?,
+                PlotMode::Analemma => plot_analemma(&solar_system, pixels, time, writer)?,
             }
         },
         Command::Sign { planets, time } => {
-            let planet_names = match planets {
-                Some(pl) => pl.iter().map(|x| format!("{x:?}")).collect(),
-                None => solar_system.names().into_iter().filter(|s| *s != solar_system.zodiac_center().get_name()).collect(),
-            };
-            print_current_signs(&solar_system, planet_names, time);
+            let time = time.resolve(zone)?;
+            let planet_names = resolve_objects(&solar_system, planets)?;
+            print_current_signs(&solar_system, planet_names, time, writer);
         },
         Command::Next { sign, planets, time } => {
+            let time = time.resolve(zone)?;
+            let planet_names = resolve_objects(&solar_system, planets)?;
+            let sign = resolve_sign(&solar_system, &sign)?;
+            print_next_sign_time(&solar_system, planet_names, &sign, time, writer);
+        },
+        Command::Coords { planets, time, frame } => {
+            let time = time.resolve(zone)?;
+            let planet_names = resolve_objects(&solar_system, planets)?;
+            match frame {
+                CoordinateFrame::EclipticJ2000 => print_coordinates::<EclipticJ2000>(&solar_system, planet_names, time, false, writer),
+                CoordinateFrame::EclipticOfDate => print_coordinates::<EclipticOfDate>(&solar_system, planet_names, time, false, writer),
+                CoordinateFrame::EquatorialJ2000 => print_coordinates::<EquatorialJ2000>(&solar_system, planet_names, time, true, writer),
+                CoordinateFrame::EquatorialOfDate => print_coordinates::<EquatorialOfDate>(&solar_system, planet_names, time, true, writer),
+            }
+        },
+        Command::Sky { planets, time, refraction } => {
+            let time = time.resolve(zone)?;
+            let planet_names = resolve_objects(&solar_system, planets)?;
+            print_sky(&solar_system, planet_names, time, refraction, writer);
+        },
+        Command::Visibility { planets, time } => {
+            let time = time.resolve(zone)?;
+            let planet_names = resolve_objects(&solar_system, planets)?;
+            print_visibility(&solar_system, planet_names, time, writer);
+        },
+        Command::Riseset { planets, time, days } => {
+            let time = time.resolve(zone)?;
+            let planet_names = resolve_objects(&solar_system, planets)?;
+            print_rise_set(&solar_system, planet_names, time, days, writer)?;
+        },
+        Command::Almanac { time, days, format, output } => {
+            let time = time.resolve(zone)?;
+            print_almanac(&solar_system, time, days, format, output, writer)?;
+        },
+        Command::Moon { time, days } => {
+            let time = time.resolve(zone)?;
+            print_moon(&solar_system, time, days, writer)?;
+        },
+        Command::Lagrange { planets, time } => {
+            let time = time.resolve(zone)?;
             let planet_names = match planets {
-                Some(pl) => pl.iter().map(|x| format!("{x:?}")).collect(),
-                None => solar_system.names().into_iter().filter(|s| *s != solar_system.zodiac_center().get_name()).collect(),
+                Some(planets) => resolve_objects(&solar_system, Some(planets))?,
+                None => solar_system.objects().iter().filter(|obj| obj.orbitor().is_some()).map(|obj| obj.get_name()).collect(),
             };
-            print_next_sign_time(&solar_system, planet_names, &format!("{sign:?}"), time);
-        }
+            print_lagrange(&solar_system, planet_names, time, writer);
+        },
+        Command::Seasons { year, years } => {
+            let year = match year {
+                Some(year) => year,
+                None => CalendarDate::from_julian_date(timezone::wall(zone, parse_time("now")?.resolve(zone)?)).year,
+            };
+            print_seasons(&solar_system, year, years, writer)?;
+        },
+        Command::Eclipses { time, days } => {
+            let time = time.resolve(zone)?;
+            print_eclipses(&solar_system, time, days, writer)?;
+        },
+        Command::Occultations { time, days, geocentric } => {
+            let time = time.resolve(zone)?;
+            print_occultations(&solar_system, time, days, geocentric, writer)?;
+        },
+        Command::Events { planets, time, days, within } => {
+            let time = time.resolve(zone)?;
+            // The zodiac center has a perihelion and aphelion too
+            let planet_names = resolve_objects(&solar_system, planets.or_else(|| Some(solar_system.names())))?;
+            print_events(&solar_system, planet_names, time, days, within, writer)?;
+        },
+        Command::Align { planets, time, search } => {
+            let time = time.resolve(zone)?;
+            let planet_names = resolve_objects(&solar_system, Some(planets))?;
+            print_alignments(&solar_system, planet_names, time, &search, writer);
+        },
+        Command::Eclipse { time, map, pixels } => {
+            let time = time.resolve(zone)?;
+            print_eclipse(&solar_system, time, map, pixels, writer)?;
+        },
+        Command::Time { time } => print_time_scales(time.resolve(zone)?, writer),
+        Command::Dump { output, format } => dump_system(&solar_system, output, format)?,
     }
+    Ok(())
 }
@@ -0,0 +1,302 @@
+use std::fs;
+use std::path::Path;
+use std::rc::Rc;
+use clap::ValueEnum;
+use plotters::style::RGBColor;
+use uom::si::angular_velocity::degree_per_second;
+use uom::si::f64::*;
+
+use crate::calendar::CalendarDate;
+use crate::orbitor::{Epoch, SolarSystemObject, AU};
+use crate::photometry::MagnitudeLaw;
+
+/// Mean motion of a massless body at 1 AU from the Sun, in degrees per day (Gaussian gravitational constant)
+const GAUSSIAN_MEAN_MOTION: f64 = 0.9856076686;
+
+/// Slopes the MPC assumes when a record leaves them out: G for asteroids, and the comet slope of a 10 log r brightening
+const DEFAULT_ASTEROID_SLOPE: f64 = 0.15;
+const DEFAULT_COMET_SLOPE: f64 = 4.0;
+
+const ASTEROID_COLOR: RGBColor = RGBColor(150, 150, 150);
+const COMET_COLOR: RGBColor = RGBColor(150, 220, 220);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum MpcFormat {
+    /// Guess from the first record
+    Auto,
+    /// MPCORB.DAT and its excerpts (NEA.txt, Distant.txt, ...)
+    Mpcorb,
+    /// CometEls.txt
+    Comet,
+}
+
+/// One orbit from an MPC file, with angles in degrees and distances in AU
+#[derive(Debug, Clone, PartialEq)]
+pub struct MpcRecord {
+    pub name: String,
+    pub number: Option<u32>,
+    pub magnitude: Option<f64>,
+    pub slope: Option<f64>,
+    pub is_comet: bool,
+    /// Julian date (TT) at which the mean anomaly holds
+    pub epoch: f64,
+    pub mean_anomaly: f64,
+    pub aop: f64,
+    pub lan: f64,
+    pub inclination: f64,
+    pub eccentricity: f64,
+    pub semimajor: f64,
+    /// Degrees per day
+    pub mean_motion: f64,
+}
+
+impl MpcRecord {
+    /// The brightness law from the absolute magnitude and slope, if the record has a magnitude
+    pub fn magnitude_law(&self) -> Option<MagnitudeLaw> {
+        let h = self.magnitude?;
+        Some(if self.is_comet {
+            MagnitudeLaw::Comet { h, slope: self.slope.unwrap_or(DEFAULT_COMET_SLOPE) }
+        }
+        else {
+            MagnitudeLaw::Asteroid { h, g: self.slope.unwrap_or(DEFAULT_ASTEROID_SLOPE) }
+        })
+    }
+
+    pub fn into_object(self, parent: Rc<SolarSystemObject>) -> SolarSystemObject {
+        let color = if self.is_comet { COMET_COLOR } else { ASTEROID_COLOR };
+        let law = self.magnitude_law();
+        let obj = SolarSystemObject::new_orbitor(
+            &self.name,
+            color,
+            0.0,
+            parent,
+            self.semimajor * AU,
+            self.eccentricity,
+            self.inclination,
+            self.lan,
+            self.aop,
+            self.mean_anomaly,
+        ).with_mean_motion(AngularVelocity::new::<degree_per_second>(self.mean_motion / 86400.0))
+        .with_epoch(Epoch::JulianDate(self.epoch));
+        match law {
+            Some(law) => obj.with_magnitude(law),
+            None => obj,
+        }
+    }
+}
+
+/// Which records to keep. Empty lists and `None` don't filter anything.
+#[derive(Debug, Clone, Default)]
+pub struct MpcFilter {
+    /// Case-insensitive substrings of the name or designation
+    pub names: Vec<String>,
+    /// Minor planet numbers, or periodic comet numbers
+    pub numbers: Vec<u32>,
+    /// Faintest absolute magnitude H to keep
+    pub max_magnitude: Option<f64>,
+    /// Stop after this many matching records
+    pub limit: Option<usize>,
+}
+
+impl MpcFilter {
+    pub fn matches(&self, record: &MpcRecord) -> bool {
+        let name = record.name.to_lowercase();
+        let name_ok = self.names.is_empty() || self.names.iter().any(|n| name.contains(&n.to_lowercase()));
+        let number_ok = self.numbers.is_empty() || record.number.is_some_and(|n| self.numbers.contains(&n));
+        let magnitude_ok = match (self.max_magnitude, record.magnitude) {
+            (Some(max), Some(h)) => h <= max,
+            (Some(_), None) => false,
+            (None, _) => true,
+        };
+        name_ok && number_ok && magnitude_ok
+    }
+}
+
+/// Result of reading a file: the matching records, and how many lines could not be parsed
+#[derive(Debug, Clone, Default)]
+pub struct MpcImport {
+    pub records: Vec<MpcRecord>,
+    pub skipped: usize,
+}
+
+/// Read and filter all records of a local MPC file
+pub fn read_file(path: &Path, format: MpcFormat, filter: &MpcFilter) -> Result<MpcImport, String> {
+    let contents = fs::read_to_string(path).map_err(|e| format!("Could not read {}: {e}", path.display()))?;
+    Ok(parse(&contents, format, filter))
+}
+
+/// Parse the contents of an MPC file. Hyperbolic and parabolic comets are counted as skipped.
+pub fn parse(contents: &str, format: MpcFormat, filter: &MpcFilter) -> MpcImport {
+    // MPCORB.DAT starts with a free-form header that ends in a line of dashes
+    let body = match contents.find("\n-----") {
+        Some(idx) => contents[idx + 1..].split_once('\n').map_or("", |(_, rest)| rest),
+        None => contents,
+    };
+    let mut lines = body.lines().filter(|l| !l.trim().is_empty()).peekable();
+    let format = match format {
+        MpcFormat::Auto => lines.peek().map_or(MpcFormat::Mpcorb, |l| detect_format(l)),
+        f => f,
+    };
+    let mut import = MpcImport::default();
+    for line in lines {
+        if filter.limit.is_some_and(|limit| import.records.len() >= limit) {
+            break;
+        }
+        let record = match format {
+            MpcFormat::Comet => parse_comet_line(line),
+            _ => parse_mpcorb_line(line),
+        };
+        match record {
+            Some(r) => if filter.matches(&r) {
+                import.records.push(r);
+            },
+            None => import.skipped += 1,
+        }
+    }
+    import
+}
+
+fn detect_format(line: &str) -> MpcFormat {
+    let orbit_type = field(line, 5, 5);
+    let year = field(line, 15, 18);
+    if matches!(orbit_type, "C" | "P" | "D" | "X" | "I" | "A") && year.chars().all(|c| c.is_ascii_digit()) {
+        MpcFormat::Comet
+    }
+    else {
+        MpcFormat::Mpcorb
+    }
+}
+
+/// Parse one line of the MPCORB format
+pub fn parse_mpcorb_line(line: &str) -> Option<MpcRecord> {
+    let designation = field(line, 1, 7);
+    let readable = field(line, 167, 194);
+    let name = match readable.split_once(") ") {
+        Some((_, name)) if readable.starts_with('(') => name.trim(),
+        _ if !readable.is_empty() => readable,
+        _ => designation,
+    };
+    // Designations are plain ASCII, so anything else is a damaged line
+    if name.is_empty() || !designation.is_ascii() {
+        return None;
+    }
+    Some(MpcRecord {
+        name: name.into(),
+        number: unpack_number(designation),
+        magnitude: field(line, 9, 13).parse().ok(),
+        slope: field(line, 15, 19).parse().ok(),
+        is_comet: false,
+        epoch: unpack_epoch(field(line, 21, 25))?,
+        mean_anomaly: field(line, 27, 35).parse().ok()?,
+        aop: field(line, 38, 46).parse().ok()?,
+        lan: field(line, 49, 57).parse().ok()?,
+        inclination: field(line, 60, 68).parse().ok()?,
+        eccentricity: field(line, 71, 79).parse().ok()?,
+        mean_motion: field(line, 81, 91).parse().ok()?,
+        semimajor: field(line, 93, 103).parse().ok()?,
+    })
+}
+
+/// Parse one line of the CometEls format. Returns `None` for orbits that are not closed ellipses.
+pub fn parse_comet_line(line: &str) -> Option<MpcRecord> {
+    let perihelion_distance: f64 = field(line, 31, 39).parse().ok()?;
+    let eccentricity: f64 = field(line, 42, 49).parse().ok()?;
+    if !(0.0..1.0).contains(&eccentricity) {
+        return None;
+    }
+    let semimajor = perihelion_distance / (1.0 - eccentricity);
+    let perihelion_day: f64 = field(line, 23, 29).parse().ok()?;
+    let perihelion = julian_date(field(line, 15, 18).parse().ok()?, field(line, 20, 21).parse().ok()?, perihelion_day.floor() as u8)?
+        + perihelion_day.fract();
+    let name = field(line, 103, 158);
+    Some(MpcRecord {
+        name: if name.is_empty() { field(line, 1, 12).into() } else { name.into() },
+        number: field(line, 1, 4).parse().ok(),
+        magnitude: field(line, 92, 95).parse().ok(),
+        slope: field(line, 97, 100).parse().ok(),
+        is_comet: true,
+        epoch: perihelion,
+        mean_anomaly: 0.0,
+        aop: field(line, 52, 59).parse().ok()?,
+        lan: field(line, 62, 69).parse().ok()?,
+        inclination: field(line, 72, 79).parse().ok()?,
+        eccentricity,
+        mean_motion: GAUSSIAN_MEAN_MOTION / semimajor.powf(1.5),
+        semimajor,
+    })
+}
+
+/// Columns are 1-based and inclusive, as in the MPC format descriptions
+fn field(line: &str, start: usize, end: usize) -> &str {
+    let end = end.min(line.len());
+    line.get(start - 1..end).unwrap_or("").trim()
+}
+
+/// Digits and letters as used in packed dates and numbers: 0-9, then A-Z for 10-35, then a-z for 36-61
+fn packed_digit(c: char) -> Option<u32> {
+    match c {
+        '0'..='9' => Some(c as u32 - '0' as u32),
+        'A'..='Z' => Some(c as u32 - 'A' as u32 + 10),
+        'a'..='z' => Some(c as u32 - 'a' as u32 + 36),
+        _ => None,
+    }
+}
+
+/// Minor planet number from a packed designation like `00433`, `A0345` or `~0001`
+fn unpack_number(packed: &str) -> Option<u32> {
+    let mut chars = packed.chars();
+    let head = chars.next()?;
+    let tail = chars.as_str();
+    if tail.chars().count() != 4 {
+        return None;
+    }
+    if head == '~' {
+        return tail.chars().try_fold(0, |acc, c| Some(acc * 62 + packed_digit(c)?)).map(|n| n + 620000);
+    }
+    let tail: u32 = tail.parse().ok()?;
+    Some(packed_digit(head)? * 10000 + tail)
+}
+
+/// Julian date of a packed epoch like `K194R` (2019-04-27, 0h TT)
+fn unpack_epoch(packed: &str) -> Option<f64> {
+    let mut chars = packed.chars();
+    let century = match chars.next()? {
+        'I' => 1800,
+        'J' => 1900,
+        'K' => 2000,
+        _ => return None,
+    };
+    let year = century + chars.by_ref().take(2).collect::<String>().parse::<i32>().ok()?;
+    let month = packed_digit(chars.next()?)?;
+    let day_of_month = packed_digit(chars.next()?)?;
+    julian_date(year, month as u8, day_of_month as u8)
+}
+
+/// Julian date at 0h of a calendar date
+fn julian_date(year: i32, month: u8, day_of_month: u8) -> Option<f64> {
+    let date = CalendarDate { year, month, day: day_of_month, hour: 0, minute: 0, second: 0.0 };
+    date.validate().ok()?;
+    Some(date.julian_date())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unpacks_numbers() {
+        assert_eq!(unpack_number("00433"), Some(433));
+        assert_eq!(unpack_number("A0345"), Some(100345));
+        assert_eq!(unpack_number("~0001"), Some(620001));
+        assert_eq!(unpack_number("é0001"), None);
+        assert_eq!(unpack_number("ééé1"), None);
+    }
+
+    #[test]
+    fn skips_damaged_designations() {
+        let line = "00001    3.34  0.12 K194R  77.37209   73.59769   80.30553   10.59407  0.0760091  0.21388522   2.7691652  0 \
+                    MPO492748  6689 114 1801-2019 0.60 M-v 30k Pertrb     0000      (1) Ceres              20190321";
+        assert_eq!(parse_mpcorb_line(line).and_then(|r| r.number), Some(1));
+        assert!(parse_mpcorb_line(&format!("é0001{}", &line[5..])).is_none());
+    }
+}
@@ -0,0 +1,87 @@
+use uom::si::angle::{degree, radian};
+use uom::si::f64::*;
+use uom::si::length::meter;
+use uom::si::time::day;
+
+use crate::frames::{Cartesian, EquatorialOfDate, Matrix3};
+use crate::orbitor::{julian_centuries, normalize};
+use crate::precession::{mean_obliquity, nutation};
+use crate::timescales::tt_to_ut1;
+
+/// Equatorial radius of the WGS84 ellipsoid in meters
+pub const WGS84_EQUATORIAL_RADIUS: f64 = 6378137.0;
+
+/// Flattening of the WGS84 ellipsoid
+pub const WGS84_FLATTENING: f64 = 1.0 / 298.257223563;
+
+/// A place on the Earth's surface. Longitude is positive east of Greenwich.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Observer {
+    pub latitude: Angle,
+    pub longitude: Angle,
+    pub elevation: Length,
+}
+
+impl Observer {
+    pub fn new<T>(latitude: f64, longitude: f64, elevation: f64) -> Observer
+            where T: uom::si::angle::Unit + uom::si::angle::Conversion<f64> {
+        Observer {
+            latitude: Angle::new::<T>(latitude),
+            longitude: Angle::new::<T>(longitude),
+            elevation: Length::new::<meter>(elevation),
+        }
+    }
+
+    /// Local mean sidereal time, i.e. the right ascension of the meridian (RAMC)
+    pub fn local_sidereal_time(&self, time: Time) -> Angle {
+        normalize(greenwich_mean_sidereal_time(time) + self.longitude)
+    }
+
+    /// Local apparent sidereal time: the hour angle of the true equinox of date
+    pub fn local_apparent_sidereal_time(&self, time: Time) -> Angle {
+        normalize(greenwich_apparent_sidereal_time(time) + self.longitude)
+    }
+
+    /// Position relative to the Earth's center, fixed to the Earth, from the latitude and elevation above the WGS84 ellipsoid.
+    /// The x axis points to the Greenwich meridian on the equator and z to the north pole.
+    pub fn geocentric(&self) -> [f64; 3] {
+        let (sin_lat, cos_lat) = self.latitude.get::<radian>().sin_cos();
+        let (sin_lon, cos_lon) = self.longitude.get::<radian>().sin_cos();
+        let b_over_a = 1.0 - WGS84_FLATTENING;
+        let c = 1.0 / (cos_lat * cos_lat + b_over_a * b_over_a * sin_lat * sin_lat).sqrt();
+        let s = b_over_a * b_over_a * c;
+        let h = self.elevation.get::<meter>();
+        let equatorial = (WGS84_EQUATORIAL_RADIUS * c + h) * cos_lat;
+        [equatorial * cos_lon, equatorial * sin_lon, (WGS84_EQUATORIAL_RADIUS * s + h) * sin_lat]
+    }
+
+    /// Position relative to the Earth's center on the true equator and equinox of date, turning with the Earth.
+    /// Polar motion, a few meters at most, is left out.
+    pub fn position(&self, time: Time) -> Cartesian<EquatorialOfDate> {
+        let rotation = Matrix3::r3(-greenwich_apparent_sidereal_time(time));
+        Cartesian::from_meters(rotation.apply(self.geocentric()))
+    }
+}
+
+impl Default for Observer {
+    /// The Royal Observatory, Greenwich
+    fn default() -> Observer {
+        Observer::new::<degree>(51.4769, 0.0, 46.0)
+    }
+}
+
+/// Greenwich mean sidereal time (IAU 1982), from the Earth's rotation in UT1
+pub fn greenwich_mean_sidereal_time(time: Time) -> Angle {
+    let ut1 = tt_to_ut1(time);
+    let d = ut1.get::<day>();
+    let t = julian_centuries(ut1);
+    let theta = 280.46061837 + 360.98564736629 * d + 0.000387933 * t * t - t * t * t / 38710000.0;
+    normalize(Angle::new::<degree>(theta))
+}
+
+/// Greenwich apparent sidereal time: mean sidereal time plus the equation of the equinoxes
+pub fn greenwich_apparent_sidereal_time(time: Time) -> Angle {
+    let (dpsi, deps) = nutation(time);
+    let equation_of_equinoxes = Angle::new::<radian>(dpsi.get::<radian>() * (mean_obliquity(time) + deps).cos().value);
+    normalize(greenwich_mean_sidereal_time(time) + equation_of_equinoxes)
+}
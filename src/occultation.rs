@@ -0,0 +1,242 @@
+use uom::si::angle::radian;
+use uom::si::f64::*;
+use uom::si::length::meter;
+use uom::si::time::second;
+
+use crate::frames::{Cartesian, EclipticJ2000};
+use crate::orbitor::{SolarSystem, SolarSystemObject};
+use crate::search::{contacts, happening, minimize};
+
+/// How often the Moon's distance from each body is sampled, in seconds; it moves about half a degree an hour
+const LUNAR_STEP: f64 = 3600.0;
+
+/// How often the distance of an inner planet from the Sun is sampled
+const TRANSIT_STEP: f64 = 6.0 * 3600.0;
+
+/// Contacts are looked for this many seconds either side of the least separation, longer than any
+/// occultation or transit lasts
+const LUNAR_WINDOW: f64 = 3.0 * 3600.0;
+const TRANSIT_WINDOW: f64 = 12.0 * 3600.0;
+
+/// A nearer body passing in front of a farther one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PassageKind {
+    /// An inner planet crossing the Sun
+    Transit,
+    /// The Moon covering a planet or star
+    Occultation,
+}
+
+/// A transit or occultation, seen from the zodiac center or the observer
+#[derive(Debug, Clone, PartialEq)]
+pub struct Passage {
+    pub kind: PassageKind,
+    /// The nearer body
+    pub front: String,
+    /// The body passed in front of
+    pub behind: String,
+    /// When the centers are closest
+    pub greatest: Time,
+    /// Least distance between the centers
+    pub separation: Angle,
+    /// Whether the smaller disk never lies wholly inside the larger one
+    pub grazing: bool,
+    /// When the limbs first touch (C1) and the smaller disk is first wholly inside (C2), and the same on the way out (C3, C4)
+    pub c1: Option<Time>,
+    pub c2: Option<Time>,
+    pub c3: Option<Time>,
+    pub c4: Option<Time>,
+}
+
+impl Passage {
+    /// C1 to C4, leaving out any that don't happen
+    pub fn contacts(&self) -> Vec<(&'static str, Time)> {
+        happening([("C1", self.c1), ("C2", self.c2), ("C3", self.c3), ("C4", self.c4)])
+    }
+}
+
+/// Where the bodies are looked at from, and how to sample them
+struct Search<'a> {
+    solar_system: &'a SolarSystem,
+    topocentric: bool,
+    step: Time,
+    window: Time,
+}
+
+impl Search<'_> {
+    fn position(&self, obj: &SolarSystemObject, time: Time) -> Cartesian<EclipticJ2000> {
+        if self.topocentric {
+            self.solar_system.topocentric_position(obj, time)
+        }
+        else {
+            self.solar_system.observed_position(obj, time)
+        }
+    }
+
+    /// Angular distance between the centers, and the angular radii of the front and behind bodies, in radians
+    fn disks(&self, front: &SolarSystemObject, behind: &SolarSystemObject, time: Time) -> (f64, f64, f64) {
+        let (near, far) = (self.position(front, time), self.position(behind, time));
+        let radius = |obj: &SolarSystemObject, position: Cartesian<EclipticJ2000>| {
+            (obj.get_radius() / position.norm()).value.min(1.0).asin()
+        };
+        (near.separation(&far).get::<radian>(), radius(front, near), radius(behind, far))
+    }
+
+    /// How far the observer can be from the zodiac center, as an angle seen from a body at a distance
+    fn parallax(&self, distance: Length) -> f64 {
+        if self.topocentric {
+            (self.solar_system.zodiac_center().get_radius() / distance).value.min(1.0).asin()
+        }
+        else {
+            0.0
+        }
+    }
+
+    /// Angular distances from the zodiac center between an object and another one sampled every step from `start`
+    fn separations(&self, samples: &[Cartesian<EclipticJ2000>], obj: &SolarSystemObject, start: Time) -> Vec<f64> {
+        samples.iter().enumerate()
+            .map(|(i, sample)| sample.separation(&self.solar_system.observed_position(obj, start + self.step * i as f64)).get::<radian>())
+            .collect()
+    }
+
+    /// Find every time `front` passes in front of `behind`, given their separations from the zodiac center
+    /// every step from `start`
+    fn passages(&self, kind: PassageKind, front: &SolarSystemObject, behind: &SolarSystemObject, separations: &[f64],
+                start: Time) -> Vec<Passage> {
+        let at = |i: usize| start + self.step * i as f64;
+        let geocentric = Search { topocentric: false, ..*self };
+        let mut found = Vec::new();
+        for i in 1..separations.len().saturating_sub(1) {
+            if separations[i] > separations[i - 1] || separations[i] > separations[i + 1] {
+                continue;
+            }
+            let closest = minimize(|time| geocentric.disks(front, behind, time).0, at(i - 1), at(i + 1));
+            let (separation, front_radius, behind_radius) = geocentric.disks(front, behind, closest);
+            let parallax = self.parallax(self.solar_system.observed_position(front, closest).norm());
+            if separation >= front_radius + behind_radius + parallax {
+                continue;
+            }
+            if let Some(passage) = self.passage(kind, front, behind, closest) {
+                found.push(passage);
+            }
+        }
+        found
+    }
+
+    /// The circumstances of a pass whose least geocentric separation is at `closest`, if the disks do meet
+    fn passage(&self, kind: PassageKind, front: &SolarSystemObject, behind: &SolarSystemObject, closest: Time) -> Option<Passage> {
+        let disks = |time: Time| self.disks(front, behind, time);
+        let greatest = if self.topocentric {
+            minimize(|time| disks(time).0, closest - self.window, closest + self.window)
+        }
+        else {
+            closest
+        };
+        let (separation, front_radius, behind_radius) = disks(greatest);
+        let nearer = self.position(front, greatest).norm() < self.position(behind, greatest).norm();
+        if !nearer || separation >= front_radius + behind_radius {
+            return None;
+        }
+        let (c1, c4) = contacts(greatest, self.window, |time| {
+            let (separation, front, behind) = disks(time);
+            separation - front - behind
+        });
+        let has_inside = front_radius > 0.0 && behind_radius > 0.0;
+        let (c2, c3) = if has_inside {
+            contacts(greatest, self.window, |time| {
+                let (separation, front, behind) = disks(time);
+                separation - (front - behind).abs()
+            })
+        }
+        else {
+            (None, None)
+        };
+        Some(Passage {
+            kind,
+            front: front.get_name(),
+            behind: behind.get_name(),
+            greatest,
+            separation: Angle::new::<radian>(separation),
+            grazing: has_inside && separation > (front_radius - behind_radius).abs(),
+            c1,
+            c2,
+            c3,
+            c4,
+        })
+    }
+}
+
+/// Sample an object's position from the zodiac center every step between two times
+fn samples(solar_system: &SolarSystem, obj: &SolarSystemObject, start: Time, end: Time, step: Time) -> Vec<Cartesian<EclipticJ2000>> {
+    let count = ((end - start) / step).value.ceil() as usize + 1;
+    (0..count).map(|i| solar_system.observed_position(obj, start + step * i as f64)).collect()
+}
+
+/// Find transits across the Sun of the bodies orbiting it inside the zodiac center's orbit, like Mercury and Venus,
+/// seen from the observer or, with `topocentric` false, from the zodiac center
+pub fn transits_between(solar_system: &SolarSystem, start: Time, end: Time, topocentric: bool) -> Result<Vec<Passage>, String> {
+    let sun = solar_system.get("Sun").ok_or("Transits need an object named Sun")?;
+    if sun.get_radius().get::<meter>() <= 0.0 {
+        return Err("Transits need the Sun to have a radius".into());
+    }
+    let center = solar_system.zodiac_center();
+    let center_orbit = center.orbitor().ok_or("Transits need the zodiac center to orbit the Sun")?;
+    let search = Search {
+        solar_system,
+        topocentric,
+        step: Time::new::<second>(TRANSIT_STEP),
+        window: Time::new::<second>(TRANSIT_WINDOW),
+    };
+    // Start a step early so that a transit right at the start is bracketed
+    let first = start - search.step;
+    let sun_samples = samples(solar_system, sun, first, end + search.step, search.step);
+    let mut found = Vec::new();
+    for obj in solar_system.objects() {
+        let inner = obj.orbitor().is_some_and(|orbit| {
+            std::ptr::eq(orbit.parent().as_ref(), sun) && orbit.semimajor(start) < center_orbit.semimajor(start)
+        });
+        if inner {
+            let separations = search.separations(&sun_samples, obj, first);
+            found.extend(search.passages(PassageKind::Transit, obj, sun, &separations, first));
+        }
+    }
+    Ok(finish(found, start, end))
+}
+
+/// Find occultations of planets, other bodies and stars by the Moon, seen from the observer or, with `topocentric`
+/// false, from the zodiac center
+pub fn occultations_between(solar_system: &SolarSystem, start: Time, end: Time, topocentric: bool) -> Result<Vec<Passage>, String> {
+    let moon = solar_system.get("Moon").ok_or("Occultations need an object named Moon")?;
+    if moon.get_radius().get::<meter>() <= 0.0 {
+        return Err("Occultations need the Moon to have a radius".into());
+    }
+    let search = Search {
+        solar_system,
+        topocentric,
+        step: Time::new::<second>(LUNAR_STEP),
+        window: Time::new::<second>(LUNAR_WINDOW),
+    };
+    let first = start - search.step;
+    let moon_samples = samples(solar_system, moon, first, end + search.step, search.step);
+    let center = solar_system.zodiac_center();
+    let mut found = Vec::new();
+    for obj in solar_system.objects() {
+        let target = match obj.as_ref() {
+            SolarSystemObject::Orbit { .. } => !std::ptr::eq(obj.as_ref(), moon) && !std::ptr::eq(obj.as_ref(), center),
+            SolarSystemObject::Star { .. } => true,
+            SolarSystemObject::Static { .. } | SolarSystemObject::Calculated { .. } | SolarSystemObject::Lagrange { .. } => false,
+        };
+        if target {
+            let separations = search.separations(&moon_samples, obj, first);
+            found.extend(search.passages(PassageKind::Occultation, moon, obj, &separations, first));
+        }
+    }
+    Ok(finish(found, start, end))
+}
+
+/// Keep the passages within the time range, in order
+fn finish(mut found: Vec<Passage>, start: Time, end: Time) -> Vec<Passage> {
+    found.retain(|passage| start <= passage.greatest && passage.greatest < end);
+    found.sort_by(|a, b| a.greatest.partial_cmp(&b.greatest).unwrap());
+    found
+}
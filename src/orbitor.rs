@@ -5,15 +5,30 @@ use std::collections::HashMap;
 use std::f64::consts::TAU;
 use plotters::prelude::*;
 use plotters::style::{RGBColor, full_palette::{GREY, PURPLE, BLUE_300, ORANGE, BLUE_100}};
-use time::{OffsetDateTime, macros::datetime};
+use time::OffsetDateTime;
 use uom::si::angle::{degree, radian};
+use uom::si::angular_velocity::{degree_per_second, radian_per_second};
 use uom::si::mass::kilogram;
 use uom::si::ratio::ratio;
 use uom::si::time::{day, second};
-use uom::si::length::meter;
+use uom::si::length::{astronomical_unit, kilometer, meter};
+use uom::si::velocity::meter_per_second;
+use uom::si::frequency::hertz;
 use uom::si::{f64::*, Quantity};
 use uom::typenum::{Z0, P3, N1, N2};
 
+use crate::apparent::{observed_position, Positions};
+use crate::frames::{Cartesian, EclipticJ2000, EclipticOfDate, Equinox, EquatorialOfDate, Frame, Spherical};
+use crate::horizon::Horizontal;
+use crate::lagrange::LagrangePoint;
+use crate::lunar;
+use crate::observer::Observer;
+use crate::photometry::MagnitudeLaw;
+use crate::points::CalculatedPoint;
+use crate::precession::nutation;
+use crate::stars::FixedStar;
+use crate::timescales::{tt_to_tdb, utc_to_tt};
+
 // #[derive(Clone, Copy, Debug)]
 // struct Time(f64);
 
@@ -36,7 +51,7 @@ use uom::typenum::{Z0, P3, N1, N2};
 //     }
 // }
 
-const SCALING_FACTOR: f64 = 25000000000.0;
+pub const SCALING_FACTOR: f64 = 25000000000.0;
 
 // /// Convert degrees to radians
 // pub fn deg_to_rad(x: f64) -> f64 {
@@ -52,34 +67,65 @@ pub fn normalize(ang: Angle) -> Angle {
     Angle::new::<radian>(ang.get::<radian>().rem_euclid(TAU))
 }
 
-pub const J2000: OffsetDateTime = datetime!(2000-01-01 12:00 UTC);
+/// Astronomical unit in meters
+pub const AU: f64 = 149597870700.0;
+
+pub const JULIAN_CENTURY: f64 = 36525.0;
+
+/// Julian centuries since J2000, the time argument of most astronomical series
+pub fn julian_centuries(time: Time) -> f64 {
+    time.get::<day>() / JULIAN_CENTURY
+}
+
+/// Angular rate given in degrees per Julian century, the way ephemeris tables publish them
+pub fn degrees_per_century(rate: f64) -> AngularVelocity {
+    AngularVelocity::new::<degree_per_second>(rate / (JULIAN_CENTURY * 86400.0))
+}
+
+/// Angular rate given in arcseconds per Julian year, the way secular frequencies are published
+pub fn arcseconds_per_year(rate: f64) -> AngularVelocity {
+    degrees_per_century(rate * 100.0 / 3600.0)
+}
+
+/// Julian date of J2000, the origin of the simulation's time variable (TT)
+pub const J2000_JD: f64 = 2451545.0;
 
-pub fn dt_to_internal(dt: OffsetDateTime) -> Time {
-    let (dur, sign) = if J2000 > dt {
-        (J2000 - dt, -1.0)
-    } 
-    else {
-        (dt - J2000, 1.0)
-    };
-    let t: Time = std::time::Duration::try_from(dur).expect("Duration conversion should not violate base type range")
-        .try_into().expect("Duration should have been constructed non-negative");
-    t * sign
+/// The instant at which a set of orbital elements holds
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Epoch {
+    JulianDate(f64),
+    DateTime(OffsetDateTime),
 }
 
-pub fn internal_to_dt(time: Time) -> OffsetDateTime {
-    let dur = std::time::Duration::try_from(time.abs()).expect("`time.abs()` should be non-negative");
-    if time.is_sign_negative() {
-        J2000 - dur
+impl Epoch {
+    pub fn to_internal(self) -> Time {
+        match self {
+            Epoch::JulianDate(jd) => jd_to_internal(jd),
+            Epoch::DateTime(dt) => utc_to_tt(dt),
+        }
     }
-    else {
-        J2000 + dur
+}
+
+impl From<OffsetDateTime> for Epoch {
+    fn from(dt: OffsetDateTime) -> Epoch {
+        Epoch::DateTime(dt)
     }
 }
 
+pub fn jd_to_internal(jd: f64) -> Time {
+    Time::new::<day>(jd - J2000_JD)
+}
+
+pub fn internal_to_jd(time: Time) -> f64 {
+    time.get::<day>() + J2000_JD
+}
+
+
 
 
 // const G: f64 = 6.67430e-11;
-const G: Quantity<uom::si::ISQ<P3, N1, N2, Z0, Z0, Z0, Z0>, uom::si::SI<f64>, f64> = Quantity { dimension: PhantomData, units: PhantomData, value: 6.674e-11, };
+pub type GravitationalConstant = Quantity<uom::si::ISQ<P3, N1, N2, Z0, Z0, Z0, Z0>, uom::si::SI<f64>, f64>;
+pub const G: GravitationalConstant = Quantity { dimension: PhantomData, units: PhantomData, value: 6.674e-11, };
 
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub struct Point3D(pub Length, pub Length, pub Length);
@@ -144,13 +190,18 @@ impl From<Point3D> for Point2D {
 }
 
 pub trait Locatable {
-    fn xyz(&self, time: Time) -> Point3D;
-    fn xy(&self, time: Time) -> Point2D;
-    fn angle(&self, other: &impl Locatable, time: Time) -> Angle {
-        let Point2D(x, y) = self.xy(time);
-        let Point2D(x2, y2) = other.xy(time);
-        // ((y - y2).atan2(x - x2).get::<radian>() + TAU) % TAU
-        normalize((y - y2).atan2(x - x2))
+    /// True position in meters, relative to the system's static root, in the J2000 ecliptic frame
+    fn position(&self, time: Time) -> Cartesian<EclipticJ2000>;
+    /// Position scaled down to plot coordinates
+    fn xyz(&self, time: Time) -> Point3D {
+        self.position(time).to_plot()
+    }
+    fn xy(&self, time: Time) -> Point2D {
+        self.xyz(time).into()
+    }
+    /// Position as seen from another object
+    fn position_from(&self, other: &impl Locatable, time: Time) -> Cartesian<EclipticJ2000> {
+        self.position(time) - other.position(time)
     }
     // fn angle_rad(&self, other: &impl Locatable, time: Time) -> f64 {
     //     let Point2D(x, y) = self.xy(time);
@@ -163,8 +214,12 @@ pub trait Locatable {
     // }
 }
 
+/// An object fixed in place, at a position in meters in the J2000 ecliptic frame
 pub struct StaticObject {
     mass: Mass,
+    radius: Length,
+    albedo: f64,
+    magnitude: Option<MagnitudeLaw>,
     x: Length,
     y: Length,
     z: Length
@@ -174,15 +229,40 @@ impl StaticObject {
     pub fn new(mass: Mass, x: Length, y: Length, z: Length) -> StaticObject {
         StaticObject {
             mass,
+            radius: Length::new::<meter>(0.0),
+            albedo: 0.0,
+            magnitude: None,
             x,
             y,
             z,
         }
     }
+
+    pub fn mass(&self) -> Mass {
+        self.mass
+    }
+
+    pub fn with_radius(mut self, radius: Length) -> StaticObject {
+        self.radius = radius;
+        self
+    }
+
+    pub fn with_albedo(mut self, albedo: f64) -> StaticObject {
+        self.albedo = albedo;
+        self
+    }
+
+    pub fn with_magnitude(mut self, magnitude: MagnitudeLaw) -> StaticObject {
+        self.magnitude = Some(magnitude);
+        self
+    }
 }
 
 pub struct Orbitor {
     mass: Mass,
+    radius: Length,
+    albedo: f64, //geometric albedo
+    magnitude: Option<MagnitudeLaw>,
     parent: Rc<SolarSystemObject>,
     semimajor: Length,
     eccentricity: f64,
@@ -190,9 +270,28 @@ pub struct Orbitor {
     lan: Angle, //longitude of the ascending node
     aop: Angle, //argument of periapsis
     mae: Angle, //mean anomaly at epoch
+    semimajor_rate: Velocity,
+    eccentricity_rate: Frequency,
+    inclination_rate: AngularVelocity,
+    lan_rate: AngularVelocity, //nodal precession
+    aop_rate: AngularVelocity, //apsidal precession
+    mean_motion: Option<AngularVelocity>, //overrides the two-body mean motion
+    epoch: Time, //time at which the elements hold
+    perturbations: Perturbations,
+}
+
+/// A theory of motion that gives a body's position in place of its Keplerian orbit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Perturbations {
+    #[default]
+    None,
+    /// The Moon's position from the main terms of its theory (Meeus ch. 47) in place of the orbit,
+    /// which still gives its mean node and apogee
+    Lunar,
 }
 
 impl Orbitor {
+    #[allow(clippy::too_many_arguments)]
     pub fn new<T>(
         mass: f64,
         parent: Rc<SolarSystemObject>, 
@@ -205,6 +304,9 @@ impl Orbitor {
             where T: uom::si::angle::Unit + uom::si::angle::Conversion<f64> {
         Orbitor {
             mass: Mass::new::<kilogram>(mass),
+            radius: Length::new::<meter>(0.0),
+            albedo: 0.0,
+            magnitude: None,
             parent,
             semimajor: Length::new::<meter>(semimajor),
             eccentricity,
@@ -212,9 +314,167 @@ impl Orbitor {
             lan: Angle::new::<T>(lan),
             aop: Angle::new::<T>(aop),
             mae: Angle::new::<T>(mae),
+            semimajor_rate: Velocity::new::<meter_per_second>(0.0),
+            eccentricity_rate: Frequency::new::<hertz>(0.0),
+            inclination_rate: AngularVelocity::new::<radian_per_second>(0.0),
+            lan_rate: AngularVelocity::new::<radian_per_second>(0.0),
+            aop_rate: AngularVelocity::new::<radian_per_second>(0.0),
+            mean_motion: None,
+            epoch: Time::new::<second>(0.0),
+            perturbations: Perturbations::None,
         }
     }
 
+    /// Set the time at which the elements (and the mean anomaly in particular) are given.
+    /// Orbits default to J2000.
+    pub fn with_epoch(mut self, epoch: impl Into<Epoch>) -> Orbitor {
+        self.epoch = epoch.into().to_internal();
+        self
+    }
+
+    pub fn epoch(&self) -> Time {
+        self.epoch
+    }
+
+    /// Let the orbital plane and the periapsis drift over time
+    pub fn with_precession(mut self, lan_rate: AngularVelocity, aop_rate: AngularVelocity) -> Orbitor {
+        self.lan_rate = lan_rate;
+        self.aop_rate = aop_rate;
+        self
+    }
+
+    /// Build an orbit from mean elements with linear rates, as published in JPL's approximate planetary positions.
+    /// Semimajor axes are in AU, angles in degrees, and rates per Julian century.
+    pub fn from_mean_elements(mass: f64, parent: Rc<SolarSystemObject>, elements: &MeanElements) -> Orbitor {
+        let MeanElements { semimajor, eccentricity, inclination, mean_longitude, lon_perihelion, lan } = *elements;
+        let century = Time::new::<day>(JULIAN_CENTURY);
+        Orbitor::new::<degree>(
+            mass,
+            parent,
+            Length::new::<astronomical_unit>(semimajor.0).get::<meter>(),
+            eccentricity.0,
+            inclination.0,
+            lan.0,
+            lon_perihelion.0 - lan.0,
+            mean_longitude.0 - lon_perihelion.0,
+        ).with_precession(
+            degrees_per_century(lan.1),
+            degrees_per_century(lon_perihelion.1 - lan.1),
+        ).with_mean_motion(degrees_per_century(mean_longitude.1 - lon_perihelion.1))
+        .with_secular_rates(
+            Length::new::<astronomical_unit>(semimajor.1) / century,
+            eccentricity.1 / century,
+            degrees_per_century(inclination.1),
+        )
+    }
+
+    /// Let the size, shape and tilt of the orbit change linearly over time
+    pub fn with_secular_rates(mut self, semimajor_rate: Velocity, eccentricity_rate: Frequency, inclination_rate: AngularVelocity) -> Orbitor {
+        self.semimajor_rate = semimajor_rate;
+        self.eccentricity_rate = eccentricity_rate;
+        self.inclination_rate = inclination_rate;
+        self
+    }
+
+    /// Use an observed mean motion instead of the one implied by the masses and semimajor axis
+    pub fn with_mean_motion(mut self, mean_motion: AngularVelocity) -> Orbitor {
+        self.mean_motion = Some(mean_motion);
+        self
+    }
+
+    pub fn with_radius(mut self, radius: Length) -> Orbitor {
+        self.radius = radius;
+        self
+    }
+
+    pub fn with_albedo(mut self, albedo: f64) -> Orbitor {
+        self.albedo = albedo;
+        self
+    }
+
+    /// Set how the body's brightness changes with its distances and phase
+    pub fn with_magnitude(mut self, magnitude: MagnitudeLaw) -> Orbitor {
+        self.magnitude = Some(magnitude);
+        self
+    }
+
+    pub fn with_perturbations(mut self, perturbations: Perturbations) -> Orbitor {
+        self.perturbations = perturbations;
+        self
+    }
+
+    pub fn perturbations(&self) -> Perturbations {
+        self.perturbations
+    }
+
+    pub fn parent(&self) -> &Rc<SolarSystemObject> {
+        &self.parent
+    }
+
+    /// Semimajor axis at a given time
+    pub fn semimajor(&self, time: Time) -> Length {
+        self.semimajor + self.semimajor_rate * (time - self.epoch)
+    }
+
+    /// Eccentricity at a given time
+    pub fn eccentricity(&self, time: Time) -> f64 {
+        self.eccentricity + (self.eccentricity_rate * (time - self.epoch)).get::<ratio>()
+    }
+
+    /// Inclination at a given time
+    pub fn inclination(&self, time: Time) -> Angle {
+        self.inclination + drift(self.inclination_rate, time - self.epoch)
+    }
+
+    /// Longitude of the ascending node at a given time
+    pub fn lan(&self, time: Time) -> Angle {
+        normalize(self.lan + drift(self.lan_rate, time - self.epoch))
+    }
+
+    /// Argument of periapsis at a given time
+    pub fn aop(&self, time: Time) -> Angle {
+        normalize(self.aop + drift(self.aop_rate, time - self.epoch))
+    }
+
+    pub fn lan_rate(&self) -> AngularVelocity {
+        self.lan_rate
+    }
+
+    pub fn aop_rate(&self) -> AngularVelocity {
+        self.aop_rate
+    }
+
+    pub fn semimajor_rate(&self) -> Velocity {
+        self.semimajor_rate
+    }
+
+    pub fn eccentricity_rate(&self) -> Frequency {
+        self.eccentricity_rate
+    }
+
+    pub fn inclination_rate(&self) -> AngularVelocity {
+        self.inclination_rate
+    }
+
+    /// Mean motion, if it was set explicitly rather than derived from the masses
+    pub fn mean_motion(&self) -> Option<AngularVelocity> {
+        self.mean_motion
+    }
+
+    pub fn mass(&self) -> Mass {
+        self.mass
+    }
+
+    /// Mean anomaly at the epoch of the elements
+    pub fn mean_anomaly_at_epoch(&self) -> Angle {
+        self.mae
+    }
+
+    /// Mean longitude (node + argument of periapsis + mean anomaly) at a given time
+    pub fn mean_longitude(&self, time: Time) -> Angle {
+        normalize(self.lan(time) + self.aop(time) + self.current_mean_anomaly(time))
+    }
+
     pub fn orbital_period(&self, time: Time) -> Time {
         match self.parent.orbital_period(time) {
             Some(period) => period,
@@ -225,61 +485,108 @@ impl Orbitor {
         }
     }
 
+    /// Radius of the Hill sphere, inside which the body's gravity holds on to satellites against its parent's,
+    /// at the mean distance; it shrinks and grows with the distance around an eccentric orbit
+    pub fn hill_radius(&self, time: Time) -> Length {
+        let fraction = (self.mass / (3.0 * self.parent.get_mass())).value;
+        self.semimajor(time) * fraction.cbrt()
+    }
+
+    /// Radius of Laplace's sphere of influence, inside which orbits are best reckoned around the body rather than its parent
+    pub fn sphere_of_influence(&self, time: Time) -> Length {
+        let fraction = (self.mass / self.parent.get_mass()).value;
+        self.semimajor(time) * fraction.powf(0.4)
+    }
+
     pub fn current_mean_anomaly(&self, time: Time) -> Angle {
         if self.semimajor.get::<meter>() == 0.0 {
             Angle::new::<radian>(0.0)
         }
-        else if time == Time::new::<second>(0.0) {
+        else if time == self.epoch {
             self.mae
         }
+        else if let Some(mean_motion) = self.mean_motion {
+            normalize(self.mae + drift(mean_motion, time - self.epoch))
+        }
         else {
             let mu = G * (self.mass + self.parent.get_mass());
-            let orbital_fraction = time * (mu/(self.semimajor * self.semimajor * self.semimajor)).sqrt();
+            let orbital_fraction = (time - self.epoch) * (mu/(self.semimajor * self.semimajor * self.semimajor)).sqrt();
             let angle = normalize(Angle::from(orbital_fraction));
             normalize(self.mae + angle) //time.get::<second>() * (mu/(self.semimajor * self.semimajor * self.semimajor).sqrt())) //% TAU
         }
     }
 
-    pub fn eccentric_anomaly(&self, mean_anomaly: Angle) -> Angle {
-        let mut ecc = mean_anomaly.get::<radian>();
-        for _ in 0..5 {
-            ecc = ecc - (ecc - self.eccentricity * ecc.sin() - mean_anomaly.get::<radian>())/(1.0 - self.eccentricity * ecc.cos());
+    pub fn eccentric_anomaly(&self, mean_anomaly: Angle, time: Time) -> Angle {
+        let eccentricity = self.eccentricity(time);
+        let mean = mean_anomaly.get::<radian>();
+        // Starting from pi keeps Newton's method from overshooting on comet-like orbits
+        let mut ecc = if eccentricity > 0.8 { std::f64::consts::PI } else { mean };
+        for _ in 0..50 {
+            let step = (ecc - eccentricity * ecc.sin() - mean)/(1.0 - eccentricity * ecc.cos());
+            ecc -= step;
+            if step.abs() < 1e-12 {
+                break;
+            }
         }
         normalize(Angle::new::<radian>(ecc))
     }
     
-    pub fn true_anomaly(&self, eccentric_anomaly: Angle) -> Angle {
-        let left_term = (1.0 + self.eccentricity).sqrt() * (eccentric_anomaly/2.0).sin();
-        let right_term = (1.0 - self.eccentricity).sqrt() * (eccentric_anomaly/2.0).cos();
+    pub fn true_anomaly(&self, eccentric_anomaly: Angle, time: Time) -> Angle {
+        let eccentricity = self.eccentricity(time);
+        let left_term = (1.0 + eccentricity).sqrt() * (eccentric_anomaly/2.0).sin();
+        let right_term = (1.0 - eccentricity).sqrt() * (eccentric_anomaly/2.0).cos();
         normalize(2.0 * left_term.atan2(right_term))
     }
     
     pub fn orbit_xy(&self, time: Time) -> Point2D {
         let mean_anom = self.current_mean_anomaly(time);
-        let ecc_anom = self.eccentric_anomaly(mean_anom);
-        let true_anom = self.true_anomaly(ecc_anom);
-        let radius = self.semimajor * (1.0 - self.eccentricity * ecc_anom.cos().get::<ratio>()); //TODO
+        let ecc_anom = self.eccentric_anomaly(mean_anom, time);
+        let true_anom = self.true_anomaly(ecc_anom, time);
+        let radius = self.semimajor(time) * (1.0 - self.eccentricity(time) * ecc_anom.cos().get::<ratio>()); //TODO
         Point2D(radius * true_anom.cos(), radius * true_anom.sin())
     }
     
-    pub fn in_parent_coordinates(&self, orbit_loc: Point2D) -> Point3D {
+    /// Rotate a position in the orbital plane into the J2000 ecliptic, relative to the parent
+    pub fn in_parent_coordinates(&self, orbit_loc: Point2D, time: Time) -> Cartesian<EclipticJ2000> {
         let Point2D(ox, oy) = orbit_loc;
-        let aopcos = self.aop.cos();
-        let aopsin = self.aop.sin();
-        let lancos = self.lan.cos();
-        let lansin = self.lan.sin();
-        let inccos = self.inclination.cos();
-        let incsin = self.inclination.sin();
+        let aop = self.aop(time);
+        let lan = self.lan(time);
+        let aopcos = aop.cos();
+        let aopsin = aop.sin();
+        let lancos = lan.cos();
+        let lansin = lan.sin();
+        let inclination = self.inclination(time);
+        let inccos = inclination.cos();
+        let incsin = inclination.sin();
         let x = ox * (aopcos * lancos - aopsin * inccos * lansin) - oy * (aopsin * lancos + aopcos * inccos * lansin);
         let y = ox * (aopcos * lansin + aopsin * inccos * lancos) + oy * (aopcos * inccos * lancos - aopsin * lansin);
         let z = ox * aopsin * incsin + oy * aopcos * incsin;
-        Point3D(x, z, y)
+        Cartesian::new(x, y, z)
     }
 }
 
+/// Orbital elements and their rates per Julian century, as `(value, rate)` pairs.
+/// Semimajor axis is in AU and angles are in degrees.
+#[derive(Debug, Clone, Copy)]
+pub struct MeanElements {
+    pub semimajor: (f64, f64),
+    pub eccentricity: (f64, f64),
+    pub inclination: (f64, f64),
+    pub mean_longitude: (f64, f64),
+    pub lon_perihelion: (f64, f64),
+    pub lan: (f64, f64),
+}
+
+fn drift(rate: AngularVelocity, time: Time) -> Angle {
+    Angle::new::<radian>(rate.get::<radian_per_second>() * time.get::<second>())
+}
+
 pub enum SolarSystemObject {
     Static { name: String, color: RGBColor, s: StaticObject },
     Orbit { name: String, color: RGBColor, o: Orbitor },
+    Calculated { name: String, color: RGBColor, p: CalculatedPoint },
+    Star { name: String, color: RGBColor, f: FixedStar },
+    Lagrange { name: String, color: RGBColor, l: LagrangePoint },
     // Variable { name: String, color: RGBColor, f: & dyn Fn(f64) -> Orbitor}
 }
 
@@ -292,6 +599,7 @@ impl SolarSystemObject {
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn new_orbitor(name: &str,
                        color: RGBColor,
                        mass: f64,
@@ -310,6 +618,104 @@ impl SolarSystemObject {
         }
     }
 
+    pub fn new_mean_elements(name: &str, color: RGBColor, mass: f64, parent: Rc<SolarSystemObject>, elements: MeanElements) -> SolarSystemObject {
+        SolarSystemObject::Orbit {
+            name: name.into(),
+            color,
+            o: Orbitor::from_mean_elements(mass, parent, &elements)
+        }
+    }
+
+    pub fn new_calculated(name: &str, color: RGBColor, point: CalculatedPoint) -> SolarSystemObject {
+        SolarSystemObject::Calculated {
+            name: name.into(),
+            color,
+            p: point,
+        }
+    }
+
+    pub fn new_star(name: &str, color: RGBColor, star: FixedStar) -> SolarSystemObject {
+        SolarSystemObject::Star {
+            name: name.into(),
+            color,
+            f: star,
+        }
+    }
+
+    pub fn new_lagrange(name: &str, color: RGBColor, point: LagrangePoint) -> SolarSystemObject {
+        SolarSystemObject::Lagrange {
+            name: name.into(),
+            color,
+            l: point,
+        }
+    }
+
+    /// Apply nodal and apsidal precession to an orbiting object; other objects are returned unchanged
+    pub fn with_precession(self, lan_rate: AngularVelocity, aop_rate: AngularVelocity) -> SolarSystemObject {
+        match self {
+            Self::Orbit { name, color, o } => Self::Orbit { name, color, o: o.with_precession(lan_rate, aop_rate) },
+            other => other,
+        }
+    }
+
+    /// Override the mean motion of an orbiting object; other objects are returned unchanged
+    pub fn with_mean_motion(self, mean_motion: AngularVelocity) -> SolarSystemObject {
+        match self {
+            Self::Orbit { name, color, o } => Self::Orbit { name, color, o: o.with_mean_motion(mean_motion) },
+            other => other,
+        }
+    }
+
+    /// Set the mean radius of a body; calculated points and stars are returned unchanged
+    pub fn with_radius(self, radius: Length) -> SolarSystemObject {
+        match self {
+            Self::Static { name, color, s } => Self::Static { name, color, s: s.with_radius(radius) },
+            Self::Orbit { name, color, o } => Self::Orbit { name, color, o: o.with_radius(radius) },
+            other => other,
+        }
+    }
+
+    /// Set the geometric albedo of a body; calculated points and stars are returned unchanged
+    pub fn with_albedo(self, albedo: f64) -> SolarSystemObject {
+        match self {
+            Self::Static { name, color, s } => Self::Static { name, color, s: s.with_albedo(albedo) },
+            Self::Orbit { name, color, o } => Self::Orbit { name, color, o: o.with_albedo(albedo) },
+            other => other,
+        }
+    }
+
+    /// Set the magnitude law of a body; calculated points and stars, which have catalogue magnitudes, are returned unchanged
+    pub fn with_magnitude(self, magnitude: MagnitudeLaw) -> SolarSystemObject {
+        match self {
+            Self::Static { name, color, s } => Self::Static { name, color, s: s.with_magnitude(magnitude) },
+            Self::Orbit { name, color, o } => Self::Orbit { name, color, o: o.with_magnitude(magnitude) },
+            other => other,
+        }
+    }
+
+    /// Take an orbiting object's position from a theory of its motion; other objects are returned unchanged
+    pub fn with_perturbations(self, perturbations: Perturbations) -> SolarSystemObject {
+        match self {
+            Self::Orbit { name, color, o } => Self::Orbit { name, color, o: o.with_perturbations(perturbations) },
+            other => other,
+        }
+    }
+
+    /// Set the epoch of an orbiting object's elements; other objects are returned unchanged
+    pub fn with_epoch(self, epoch: impl Into<Epoch>) -> SolarSystemObject {
+        match self {
+            Self::Orbit { name, color, o } => Self::Orbit { name, color, o: o.with_epoch(epoch) },
+            other => other,
+        }
+    }
+
+    pub fn orbitor(&self) -> Option<&Orbitor> {
+        match self {
+            Self::Orbit { o, .. } => Some(o),
+            _ => None,
+        }
+    }
+
     // pub fn new_variable(name: &str, color: RGBColor, function: &dyn Fn(f64) -> Orbitor) -> SolarSystemObject {
     //     SolarSystemObject::Variable { 
     //         name: name.into(), 
@@ -322,6 +728,9 @@ impl SolarSystemObject {
         match self {
             Self::Static { name, .. } => name.clone(),
             Self::Orbit { name, .. } => name.clone(),
+            Self::Calculated { name, .. } => name.clone(),
+            Self::Star { name, .. } => name.clone(),
+            Self::Lagrange { name, .. } => name.clone(),
             // Self::Variable { name, .. } => name.clone(),
         }
     }
@@ -330,6 +739,9 @@ impl SolarSystemObject {
         match self {
             Self::Static { color, .. } => *color,
             Self::Orbit { color, .. } => *color,
+            Self::Calculated { color, .. } => *color,
+            Self::Star { color, .. } => *color,
+            Self::Lagrange { color, .. } => *color,
             // Self::Variable { color, .. } => *color,
         }
     }
@@ -338,21 +750,52 @@ impl SolarSystemObject {
         match self {
             Self::Static { s, .. } => s.mass,
             Self::Orbit { o, .. } => o.mass,
+            Self::Calculated { .. } | Self::Star { .. } | Self::Lagrange { .. } => Mass::new::<kilogram>(0.0),
             // Self::Variable { f, .. } => f(0.0).mass,
         }
     }
 
+    /// Radius of the body, or zero for points, stars, Lagrange points and bodies too small to matter
+    pub fn get_radius(&self) -> Length {
+        match self {
+            Self::Static { s, .. } => s.radius,
+            Self::Orbit { o, .. } => o.radius,
+            Self::Calculated { .. } | Self::Star { .. } | Self::Lagrange { .. } => Length::new::<meter>(0.0),
+        }
+    }
+
+    /// Geometric albedo, or zero if unknown
+    pub fn get_albedo(&self) -> f64 {
+        match self {
+            Self::Static { s, .. } => s.albedo,
+            Self::Orbit { o, .. } => o.albedo,
+            Self::Calculated { .. } | Self::Star { .. } | Self::Lagrange { .. } => 0.0,
+        }
+    }
+
+    /// The magnitude law the body was given, if any
+    pub fn magnitude_law(&self) -> Option<MagnitudeLaw> {
+        match self {
+            Self::Static { s, .. } => s.magnitude,
+            Self::Orbit { o, .. } => o.magnitude,
+            Self::Calculated { .. } | Self::Star { .. } | Self::Lagrange { .. } => None,
+        }
+    }
+
     pub fn orbital_period(&self, start_time: Time) -> Option<Time> {
         match self {
             Self::Orbit { o, .. } => Some (o.orbital_period(start_time)),
-            Self::Static { .. } => None,
+            Self::Static { .. } | Self::Star { .. } => None,
+            Self::Calculated { p, .. } => Some(p.period()),
+            Self::Lagrange { l, .. } => l.body().orbital_period(start_time),
             // Self::Variable { f, .. } => Some (f(start_time).orbital_period(start_time)),
         }
     }
 
+    /// Next time `longitude` (of `other` as seen from this object) falls in a range of angles
     pub fn next_time_angle_in_range(&self, other: &SolarSystemObject,
                                     angle_start: Angle, angle_end: Angle,
-                                    start_time: Time) -> Option<Time> {
+                                    start_time: Time, longitude: impl Fn(Time) -> Angle) -> Option<Time> {
         // TODO there has to be a more efficient way to calculate this...
         let max_time = {
             let t1 = self.orbital_period(start_time).unwrap_or(Time::new::<second>(1.0));
@@ -362,11 +805,11 @@ impl SolarSystemObject {
         let mut prev_time = start_time;
         for ii in 0..=(max_time.get::<day>()) as i32 {
             let time = start_time + Time::new::<day>(ii as f64);
-            let angle = other.angle(self, time);
+            let angle = longitude(time);
             if angle_start < angle && angle < angle_end {
                 for jj in 0..(time - prev_time).get::<second>() as i32 {
                     let small_time = prev_time + Time::new::<second>(jj as f64);
-                    let small_angle = other.angle(self, small_time);
+                    let small_angle = longitude(small_time);
                     if angle_start < small_angle && small_angle < angle_end {
                         return Some(small_time);
                     }
@@ -435,6 +878,10 @@ impl Zodiac {
         normalize(Angle::new::<degree>((idx * 360) as f64 / self.signs.len() as f64))
     }
 
+    pub fn signs(&self) -> &Vec<String> {
+        &self.signs
+    }
+
     pub fn angles(&self) -> Vec<Angle> {
         let mut angles = Vec::new();
         for i in 0..self.signs.len() {
@@ -457,28 +904,46 @@ impl Zodiac {
     }
 }
 
+/// Names are looked up case-insensitively and ignoring spaces and punctuation, so "Part of Fortune" matches `PartOfFortune`
+fn index_key(name: &str) -> String {
+    name.chars().filter(|c| c.is_alphanumeric()).flat_map(char::to_lowercase).collect()
+}
+
 pub struct SolarSystem {
     objects: Vec<Rc<SolarSystemObject>>,
     index: HashMap<String, usize>,
     zodiac: Zodiac,
     zodiac_center: usize,
+    equinox: Equinox,
+    positions: Positions,
+    deflection: bool,
+    observer: Observer,
 }
 
 impl SolarSystem {
     pub fn new_custom(zodiac_signs: Vec<String>, objects: Vec<Rc<SolarSystemObject>>, zodiac_center: usize) -> SolarSystem {
         let mut index = HashMap::new();
         for (i, obj) in objects.iter().enumerate() {
-            index.insert(obj.get_name().to_lowercase(), i);
+            index.insert(index_key(&obj.get_name()), i);
         };
         SolarSystem {
             objects,
             index,
             zodiac: Zodiac::new(zodiac_signs),
-            zodiac_center
+            zodiac_center,
+            equinox: Equinox::default(),
+            positions: Positions::default(),
+            deflection: false,
+            observer: Observer::default(),
         }
     }
 
     pub fn new_default() -> SolarSystem {
+        Self::new_default_at(Observer::default())
+    }
+
+    /// The default system, with chart points like the Part of Fortune cast for the given observer
+    pub fn new_default_at(observer: Observer) -> SolarSystem {
         let zodiac = Zodiac::new(vec![
             "aries".into(),
             "taurus".into(),
@@ -499,45 +964,59 @@ impl SolarSystem {
             1.9885e30,
             0.0,
             0.0,
-            0.0);
+            0.0,
+        ).with_radius(Length::new::<kilometer>(696000.0))
+        .with_magnitude(MagnitudeLaw::Star { absolute: 4.83 });
         let sun_rc = Rc::new(sun);
-        let mercury = SolarSystemObject::new_orbitor(
+        // Planets use JPL's mean elements with rates (Standish, valid 1800-2050)
+        let mercury = SolarSystemObject::new_mean_elements(
             "Mercury",
             WHITE,
             3.3011e23,
             sun_rc.clone(),
-            5.7909e+10,
-            0.2056,
-            7.004,
-            48.331,
-            29.124,
-            174.796,
-        );
-        let venus = SolarSystemObject::new_orbitor(
+            MeanElements {
+                semimajor: (0.38709927, 3.7e-07),
+                eccentricity: (0.20563593, 1.906e-05),
+                inclination: (7.00497902, -0.00594749),
+                mean_longitude: (252.2503235, 149472.67411175),
+                lon_perihelion: (77.45779628, 0.16047689),
+                lan: (48.33076593, -0.12534081),
+            },
+        ).with_radius(Length::new::<kilometer>(2439.7))
+        .with_albedo(0.142).with_magnitude(MagnitudeLaw::Mercury);
+        let venus = SolarSystemObject::new_mean_elements(
             "Venus",
             PURPLE,
             4.8675e24,
             sun_rc.clone(),
-            108208000000.0,
-            0.06772,
-            3.39458,
-            76.68,
-            54.884,
-            50.115,
-        );
-        let earth = SolarSystemObject::new_orbitor(
+            MeanElements {
+                semimajor: (0.72333566, 3.9e-06),
+                eccentricity: (0.00677672, -4.107e-05),
+                inclination: (3.39467605, -0.0007889),
+                mean_longitude: (181.9790995, 58517.81538729),
+                lon_perihelion: (131.60246718, 0.00268329),
+                lan: (76.67984255, -0.27769418),
+            },
+        ).with_radius(Length::new::<kilometer>(6051.8))
+        .with_albedo(0.689).with_magnitude(MagnitudeLaw::Venus);
+        let earth = SolarSystemObject::new_mean_elements(
             "Earth",
             BLUE_300,
             5.97217e24,
             sun_rc.clone(),
-            149598023000.0,
-            0.0167086,
-            0.00005,
-            -11.26064, 
-            114.20783,
-            358.617,
-        );
+            MeanElements {
+                semimajor: (1.00000261, 5.62e-06),
+                eccentricity: (0.01671123, -4.392e-05),
+                inclination: (-1.531e-05, -0.01294668),
+                mean_longitude: (100.46457166, 35999.37244981),
+                lon_perihelion: (102.93768193, 0.32327364),
+                lan: (0.0, 0.0),
+            },
+        ).with_radius(Length::new::<kilometer>(6378.137))
+        .with_albedo(0.434).with_magnitude(MagnitudeLaw::Earth);
         let earth_rc = Rc::new(earth);
+        // Mean elements from Meeus ch. 47: the node regresses in 18.6 years and the perigee advances in 8.85.
+        // Meeus refers them to the equinox of date, so the node rate drops the general precession to stay in J2000.
         let moon = SolarSystemObject::new_orbitor(
             "Moon",
             GREY,
@@ -546,94 +1025,295 @@ impl SolarSystem {
             384399000.0,
             0.0549,
             5.145,
-            125.08 - 0.0 * 360.0 / (18.61 * 1000.0),
-            318.15 + 0.0 * 360.0 / (8.85 * 1000.0),
-            13.13,
+            125.0445479,
+            318.3086986,
+            134.9633964,
+        ).with_radius(Length::new::<kilometer>(1737.4))
+        .with_albedo(0.12).with_magnitude(MagnitudeLaw::Moon)
+        .with_precession(
+            degrees_per_century(-1934.1362891 - 1.3969713),
+            degrees_per_century(6003.1500178),
+        ).with_mean_motion(degrees_per_century(477198.8675055))
+        .with_perturbations(Perturbations::Lunar);
+        let moon_rc = Rc::new(moon);
+        let mean_node = SolarSystemObject::new_calculated(
+            "Mean Node",
+            GREY,
+            CalculatedPoint::MeanNode { moon: moon_rc.clone() },
+        );
+        let true_node = SolarSystemObject::new_calculated(
+            "True Node",
+            WHITE,
+            CalculatedPoint::TrueNode { moon: moon_rc.clone() },
+        );
+        let lilith = SolarSystemObject::new_calculated(
+            "Lilith",
+            BLACK,
+            CalculatedPoint::MeanApogee { moon: moon_rc.clone() },
         );
-        let mars = SolarSystemObject::new_orbitor(
+        let fortune = SolarSystemObject::new_calculated(
+            "Part of Fortune",
+            GREEN,
+            CalculatedPoint::PartOfFortune { sun: sun_rc.clone(), moon: moon_rc.clone(), observer },
+        );
+        let vertex = SolarSystemObject::new_calculated(
+            "Vertex",
+            CYAN,
+            CalculatedPoint::Vertex { center: earth_rc.clone(), observer },
+        );
+        let mars = SolarSystemObject::new_mean_elements(
             "Mars",
             RED,
             6.4171e23,
             sun_rc.clone(),
-            227939366000.0,
-            0.0934,
-            1.850,
-            49.57854,
-            286.5,
-            19.412,
-        );
-        let jupiter = SolarSystemObject::new_orbitor(
+            MeanElements {
+                semimajor: (1.52371034, 1.847e-05),
+                eccentricity: (0.0933941, 7.882e-05),
+                inclination: (1.84969142, -0.00813131),
+                mean_longitude: (-4.55343205, 19140.30268499),
+                lon_perihelion: (-23.94362959, 0.44441088),
+                lan: (49.55953891, -0.29257343),
+            },
+        ).with_radius(Length::new::<kilometer>(3396.2))
+        .with_albedo(0.170).with_magnitude(MagnitudeLaw::Mars);
+        let jupiter = SolarSystemObject::new_mean_elements(
             "Jupiter",
             ORANGE,
             1.8982e27,
             sun_rc.clone(),
-            778479000000.0,
-            0.0489,
-            1.303,
-            100.464,
-            273.867,
-            20.020,
-        );
-        let saturn = SolarSystemObject::new_orbitor(
+            MeanElements {
+                semimajor: (5.202887, -0.00011607),
+                eccentricity: (0.04838624, -0.00013253),
+                inclination: (1.30439695, -0.00183714),
+                mean_longitude: (34.39644051, 3034.74612775),
+                lon_perihelion: (14.72847983, 0.21252668),
+                lan: (100.47390909, 0.20469106),
+            },
+        ).with_radius(Length::new::<kilometer>(71492.0))
+        .with_albedo(0.538).with_magnitude(MagnitudeLaw::Jupiter);
+        let saturn = SolarSystemObject::new_mean_elements(
             "Saturn",
             RGBColor(100, 100, 0),
-            5.6834e24,
+            5.6834e26,
             sun_rc.clone(),
-            1433530000000.0,
-            0.0565,
-            2.485,
-            113.665,
-            339.392,
-            317.020,
-        );
-        let uranus = SolarSystemObject::new_orbitor(
+            MeanElements {
+                semimajor: (9.53667594, -0.0012506),
+                eccentricity: (0.05386179, -0.00050991),
+                inclination: (2.48599187, 0.00193609),
+                mean_longitude: (49.95424423, 1222.49362201),
+                lon_perihelion: (92.59887831, -0.41897216),
+                lan: (113.66242448, -0.28867794),
+            },
+        ).with_radius(Length::new::<kilometer>(60268.0))
+        .with_albedo(0.499).with_magnitude(MagnitudeLaw::Saturn);
+        let uranus = SolarSystemObject::new_mean_elements(
             "Uranus",
             BLUE_100,
             8.6810e25,
             sun_rc.clone(),
-            2.870972e12,
-            0.04717,
-            0.773,
-            74.006, 
-            96.998857,
-            142.2386,
-        );
-        let neptune = SolarSystemObject::new_orbitor(
-            "Neptune", 
+            MeanElements {
+                semimajor: (19.18916464, -0.00196176),
+                eccentricity: (0.04725744, -4.397e-05),
+                inclination: (0.77263783, -0.00242939),
+                mean_longitude: (313.23810451, 428.48202785),
+                lon_perihelion: (170.9542763, 0.40805281),
+                lan: (74.01692503, 0.04240589),
+            },
+        ).with_radius(Length::new::<kilometer>(25559.0))
+        .with_albedo(0.488).with_magnitude(MagnitudeLaw::Uranus);
+        let neptune = SolarSystemObject::new_mean_elements(
+            "Neptune",
             BLUE,
             1.02409e26,
             sun_rc.clone(),
-            4.514953e12,
-            0.008678,
-            1.77,
-            131.721,
-            273.24966,
-            256.228,
-        );
+            MeanElements {
+                semimajor: (30.06992276, 0.00026291),
+                eccentricity: (0.00859048, 5.105e-05),
+                inclination: (1.77004347, 0.00035372),
+                mean_longitude: (-55.12002969, 218.45945325),
+                lon_perihelion: (44.96476227, -0.32241464),
+                lan: (131.78422574, -0.01508378),
+            },
+        ).with_radius(Length::new::<kilometer>(24764.0))
+        .with_albedo(0.442).with_magnitude(MagnitudeLaw::Neptune);
+        let pluto = SolarSystemObject::new_mean_elements(
+            "Pluto",
+            RGBColor(180, 140, 110),
+            1.303e22,
+            sun_rc.clone(),
+            MeanElements {
+                semimajor: (39.48211675, -0.00031596),
+                eccentricity: (0.2488273, 5.17e-05),
+                inclination: (17.14001206, 4.818e-05),
+                mean_longitude: (238.92903833, 145.20780515),
+                lon_perihelion: (224.06891629, -0.04062942),
+                lan: (110.30393684, -0.01183482),
+            },
+        ).with_radius(Length::new::<kilometer>(1188.3))
+        .with_albedo(0.52).with_magnitude(MagnitudeLaw::Asteroid { h: -0.7, g: 0.15 });
+        // Minor bodies use osculating elements at their own epochs (JPL Small-Body Database). Their nodes and perihelia
+        // drift at the secular frequencies s and g of their proper elements (AstDyS), in arcseconds a year, so the
+        // node moves at s and the argument of perihelion at g - s.
+        let ceres = SolarSystemObject::new_orbitor(
+            "Ceres",
+            RGBColor(200, 200, 200),
+            9.3835e20,
+            sun_rc.clone(),
+            2.7691652 * AU,
+            0.0760091,
+            10.59407,
+            80.30553,
+            73.59769,
+            77.3721,
+        ).with_radius(Length::new::<kilometer>(469.7)).with_epoch(Epoch::JulianDate(2458600.5))
+        .with_albedo(0.090).with_magnitude(MagnitudeLaw::Asteroid { h: 3.34, g: 0.12 })
+        .with_precession(arcseconds_per_year(-59.2), arcseconds_per_year(54.3 + 59.2));
+        let pallas = SolarSystemObject::new_orbitor(
+            "Pallas",
+            RGBColor(150, 180, 200),
+            2.04e20,
+            sun_rc.clone(),
+            2.7729 * AU,
+            0.2302,
+            34.8362,
+            173.0962,
+            310.0485,
+            78.2287,
+        ).with_radius(Length::new::<kilometer>(256.0)).with_epoch(Epoch::JulianDate(2458600.5))
+        .with_albedo(0.159).with_magnitude(MagnitudeLaw::Asteroid { h: 4.13, g: 0.11 })
+        .with_precession(arcseconds_per_year(-46.4), arcseconds_per_year(-1.4 + 46.4));
+        let juno = SolarSystemObject::new_orbitor(
+            "Juno",
+            RGBColor(230, 160, 200),
+            2.67e19,
+            sun_rc.clone(),
+            2.67 * AU,
+            0.2562,
+            12.9889,
+            169.8712,
+            248.105,
+            33.0771,
+        ).with_radius(Length::new::<kilometer>(123.0)).with_epoch(Epoch::JulianDate(2458600.5))
+        .with_albedo(0.238).with_magnitude(MagnitudeLaw::Asteroid { h: 5.33, g: 0.32 })
+        .with_precession(arcseconds_per_year(-61.6), arcseconds_per_year(43.8 + 61.6));
+        let vesta = SolarSystemObject::new_orbitor(
+            "Vesta",
+            RGBColor(240, 220, 150),
+            2.59076e20,
+            sun_rc.clone(),
+            2.36151 * AU,
+            0.08874,
+            7.14043,
+            103.8514,
+            150.7285,
+            204.4686,
+        ).with_radius(Length::new::<kilometer>(262.7)).with_epoch(Epoch::JulianDate(2459000.5))
+        .with_albedo(0.423).with_magnitude(MagnitudeLaw::Asteroid { h: 3.20, g: 0.32 })
+        .with_precession(arcseconds_per_year(-39.6), arcseconds_per_year(36.9 + 39.6));
+        let chiron = SolarSystemObject::new_orbitor(
+            "Chiron",
+            RGBColor(120, 200, 120),
+            4.0e18,
+            sun_rc.clone(),
+            13.648 * AU,
+            0.3789,
+            6.9265,
+            209.2988,
+            339.2538,
+            173.4291,
+        ).with_radius(Length::new::<kilometer>(108.0)).with_epoch(Epoch::JulianDate(2459000.5))
+        .with_albedo(0.16)
+        // Chiron crosses Saturn's orbit and has no proper elements; these are the first-order secular rates for its
+        // semimajor axis, g = -s, from the giant planets
+        .with_precession(arcseconds_per_year(-14.5), arcseconds_per_year(14.5 + 14.5));
         let mut solar_system = SolarSystem {
             objects: Vec::new(),
             index: HashMap::new(),
             zodiac,
             zodiac_center: 3,
+            equinox: Equinox::default(),
+            positions: Positions::default(),
+            deflection: false,
+            observer,
         };
         solar_system.add(sun_rc);
         solar_system.add(Rc::new(mercury));
         solar_system.add(Rc::new(venus));
         solar_system.add(earth_rc);
-        solar_system.add(Rc::new(moon));
+        solar_system.add(moon_rc);
         solar_system.add(Rc::new(mars));
         solar_system.add(Rc::new(jupiter));
         solar_system.add(Rc::new(saturn));
         solar_system.add(Rc::new(uranus));
         solar_system.add(Rc::new(neptune));
+        solar_system.add(Rc::new(pluto));
+        solar_system.add(Rc::new(ceres));
+        solar_system.add(Rc::new(pallas));
+        solar_system.add(Rc::new(juno));
+        solar_system.add(Rc::new(vesta));
+        solar_system.add(Rc::new(chiron));
+        solar_system.add(Rc::new(mean_node));
+        solar_system.add(Rc::new(true_node));
+        solar_system.add(Rc::new(lilith));
+        solar_system.add(Rc::new(fortune));
+        solar_system.add(Rc::new(vertex));
         solar_system
     }
 
+    /// Measure zodiac signs from the given equinox; the default is the equinox of date
+    pub fn with_equinox(mut self, equinox: Equinox) -> SolarSystem {
+        self.equinox = equinox;
+        self
+    }
+
+    pub fn equinox(&self) -> Equinox {
+        self.equinox
+    }
+
+    /// Choose between geometric, astrometric and apparent positions; the default is apparent
+    pub fn with_positions(mut self, positions: Positions) -> SolarSystem {
+        self.positions = positions;
+        self
+    }
+
+    pub fn positions(&self) -> Positions {
+        self.positions
+    }
+
+    /// Bend light passing the Sun when computing apparent positions
+    pub fn with_deflection(mut self, deflection: bool) -> SolarSystem {
+        self.deflection = deflection;
+        self
+    }
+
+    /// Stand on the zodiac center at this place for topocentric positions; the default is Greenwich
+    pub fn with_observer(mut self, observer: Observer) -> SolarSystem {
+        self.observer = observer;
+        self
+    }
+
+    pub fn observer(&self) -> &Observer {
+        &self.observer
+    }
+
     pub fn add(&mut self, obj: Rc<SolarSystemObject>) {
-        self.index.insert(obj.get_name().to_lowercase(), self.objects.len());
+        self.index.insert(index_key(&obj.get_name()), self.objects.len());
         self.objects.push(obj);
     }
 
+    /// Add every object from an iterator, e.g. the output of an orbit catalog import.
+    /// Objects whose name is already taken are skipped; returns how many were added.
+    pub fn extend(&mut self, objects: impl IntoIterator<Item = SolarSystemObject>) -> usize {
+        let mut added = 0;
+        for obj in objects {
+            if self.get(&obj.get_name()).is_none() {
+                self.add(Rc::new(obj));
+                added += 1;
+            }
+        }
+        added
+    }
+
     pub fn objects(&self) -> &Vec<Rc<SolarSystemObject>> {
         &(self.objects)
     }
@@ -651,11 +1331,17 @@ impl SolarSystem {
 
     pub fn get(&self, obj_name: &str) -> Option<&SolarSystemObject> {
         // let string_name = obj_name.into();
-        let obj_idx = self.index.get(&obj_name.to_lowercase())?;
+        let obj_idx = self.index.get(&index_key(obj_name))?;
         let obj = self.objects.get(*obj_idx)?;
         Some(obj)
     }
 
+    /// Shared handle to an object, for use as the parent of new orbits
+    pub fn get_rc(&self, obj_name: &str) -> Option<Rc<SolarSystemObject>> {
+        let obj_idx = self.index.get(&index_key(obj_name))?;
+        self.objects.get(*obj_idx).cloned()
+    }
+
     pub fn zodiac_center(&self) -> &SolarSystemObject {
         &self.objects[self.zodiac_center]
     }
@@ -664,15 +1350,6 @@ impl SolarSystem {
         self.zodiac.get_sign(angle).clone()
     }
 
-    pub fn next_time_in_sign_dt(&self, 
-                                obj_name: &str, 
-                                sign_name: &str, 
-                                start_time: OffsetDateTime) -> Option<OffsetDateTime> {
-        let start = dt_to_internal(start_time);
-        let next = self.next_time_in_sign(obj_name, sign_name, start)?;
-        Some(internal_to_dt(next))
-    }
-
     pub fn next_time_in_sign(&self, 
                             obj_name: &str,
                             sign_name: &str, 
@@ -684,61 +1361,116 @@ impl SolarSystem {
             obj,
             angle_start,
             angle_end,
-            start_time
+            start_time,
+            |time| self.equinox.longitude(self.observed_position(obj, time), time),
         )
     }
 
-    pub fn zodiac_for_dt(&self, obj_name: &str, time: OffsetDateTime) -> Option<String> {
-        let t = dt_to_internal(time);
-        self.zodiac_for(obj_name, t)
+    pub fn zodiac_for(&self, obj_name: &str, time: Time) -> Option<String> {
+        let angle = self.longitude_for(obj_name, time)?;
+        Some(self.angle_to_sign(angle))
+    }
+
+    /// Ecliptic longitude of an object as seen from the zodiac center, measured from the system's equinox
+    pub fn longitude_for(&self, obj_name: &str, time: Time) -> Option<Angle> {
+        let obj = self.get(obj_name)?;
+        Some(self.equinox.longitude(self.observed_position(obj, time), time))
     }
 
-    pub fn zodiac_for(&self, obj_name: &str, time: Time) -> Option<String> {
+    /// Position of an object as seen from the zodiac center, corrected as set by `with_positions`
+    pub fn observed_position(&self, obj: &SolarSystemObject, time: Time) -> Cartesian<EclipticJ2000> {
+        let deflector = if self.deflection { self.get("Sun") } else { None };
+        observed_position(obj, self.zodiac_center(), time, self.positions, deflector)
+    }
+
+    /// Position of an object as seen by the observer on the surface of the zodiac center, corrected for parallax.
+    /// Calculated points are directions, so they have no parallax.
+    pub fn topocentric_position(&self, obj: &SolarSystemObject, time: Time) -> Cartesian<EclipticJ2000> {
+        let observed = self.observed_position(obj, time);
+        match obj {
+            SolarSystemObject::Calculated { .. } => observed,
+            _ => observed - self.observer.position(time).to(time),
+        }
+    }
+
+    /// Position of an object as seen by the observer, in any frame
+    pub fn topocentric_in<F: Frame>(&self, obj_name: &str, time: Time) -> Option<Cartesian<F>> {
         let obj = self.get(obj_name)?;
-        let angle = obj.angle(self.zodiac_center(), time);
-        Some(self.angle_to_sign(angle))
+        Some(self.topocentric_position(obj, time).to(time))
     }
-}
 
-impl Locatable for Orbitor {
-    fn xyz(&self, time: Time) -> Point3D {
-        let Point3D(x, y, z) = self.parent.xyz(time);
-        let Point3D(x2, y2, z2) = self.in_parent_coordinates(self.orbit_xy(time));
-        Point3D(x + x2 / SCALING_FACTOR, y + y2 / SCALING_FACTOR, z + z2 / SCALING_FACTOR)
+    /// Altitude and azimuth of an object in the observer's sky, without refraction
+    pub fn horizontal_for(&self, obj_name: &str, time: Time) -> Option<Horizontal> {
+        let position = self.topocentric_in::<EquatorialOfDate>(obj_name, time)?;
+        Some(Horizontal::from_equatorial(position, &self.observer, time))
     }
 
-    fn xy(&self, time: Time) -> Point2D {
-        let Point3D(x, _, z) = self.xyz(time);
-        // println!("{x} {y}");
-        Point2D(x, z)
+    /// Position of an object as seen from the zodiac center, in any frame
+    pub fn position_in<F: Frame>(&self, obj_name: &str, time: Time) -> Option<Cartesian<F>> {
+        let obj = self.get(obj_name)?;
+        Some(self.observed_position(obj, time).to(time))
+    }
+
+    /// Longitude, latitude and distance of an object as seen from the zodiac center, in any frame
+    pub fn coordinates_in<F: Frame>(&self, obj_name: &str, time: Time) -> Option<Spherical> {
+        Some(self.position_in::<F>(obj_name, time)?.spherical())
+    }
+
+    /// Right ascension and declination of an object on the true equator and equinox of date
+    pub fn equatorial_for(&self, obj_name: &str, time: Time) -> Option<(Angle, Angle)> {
+        let Spherical { longitude, latitude, .. } = self.coordinates_in::<EquatorialOfDate>(obj_name, time)?;
+        Some((longitude, latitude))
     }
 }
 
-impl Locatable for StaticObject {
-    fn xyz(&self, _time: Time) -> Point3D {
-        Point3D(self.x, self.y, self.z)
+impl Locatable for Orbitor {
+    fn position(&self, time: Time) -> Cartesian<EclipticJ2000> {
+        let tdb = tt_to_tdb(time);
+        let offset = match self.perturbations {
+            Perturbations::None => self.in_parent_coordinates(self.orbit_xy(tdb), tdb),
+            Perturbations::Lunar => {
+                // The theory is referred to the mean equinox of date, so nutation moves it to the true one
+                let position = lunar::geocentric_position(tdb);
+                let longitude = position.longitude + nutation(tdb).0;
+                Cartesian::<EclipticOfDate>::from_spherical(Spherical { longitude, ..position }).to(tdb)
+            },
+        };
+        self.parent.position(time) + offset
     }
+}
 
-    fn xy(&self, _time: Time) -> Point2D {
-        Point2D(self.x, self.z)
+impl Locatable for StaticObject {
+    fn position(&self, _time: Time) -> Cartesian<EclipticJ2000> {
+        Cartesian::new(self.x, self.y, self.z)
     }
 }
 
 impl Locatable for SolarSystemObject {
-    fn xyz(&self, time: Time) -> Point3D {
+    fn position(&self, time: Time) -> Cartesian<EclipticJ2000> {
         match self {
-            Self::Static { s, .. } => s.xyz(time),
-            Self::Orbit { o, .. } => o.xyz(time),
-            // Self::Variable { f, .. } => f(time).xyz(time),
+            Self::Static { s, .. } => s.position(time),
+            Self::Orbit { o, .. } => o.position(time),
+            Self::Calculated { p, .. } => p.position(time),
+            Self::Star { f, .. } => f.position(time),
+            Self::Lagrange { l, .. } => l.position(time),
+            // Self::Variable { f, .. } => f(time).position(time),
         }
     }
+}
 
-    fn xy(&self, time: Time) -> Point2D {
-        match self {
-            Self::Static { s, .. } => s.xy(time),
-            Self::Orbit { o, .. } => o.xy(time),
-            // Self::Variable { f, .. } => f(time).xy(time),
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Planet masses in Earth masses, from the NASA planetary fact sheet
+    #[test]
+    fn planet_masses() {
+        let solar_system = SolarSystem::new_default();
+        let earth = solar_system.get("Earth").unwrap().get_mass();
+        for (name, expected) in [("Mercury", 0.0553), ("Venus", 0.815), ("Mars", 0.107), ("Jupiter", 317.8), ("Saturn", 95.2),
+                              ("Uranus", 14.5), ("Neptune", 17.1)] {
+            let mass = (solar_system.get(name).unwrap().get_mass() / earth).value;
+            assert!((mass / expected - 1.0).abs() < 0.01, "{name} is {mass} Earth masses");
         }
     }
-
 }
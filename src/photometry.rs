@@ -0,0 +1,198 @@
+use uom::si::angle::{degree, radian};
+use uom::si::f64::*;
+use uom::si::length::{astronomical_unit, kilometer, meter};
+
+use crate::frames::{Cartesian, EclipticJ2000, EclipticOfDate};
+use crate::orbitor::{julian_centuries, SolarSystem, SolarSystemObject};
+
+/// Meters in ten parsecs, the distance absolute magnitudes of stars are given at
+const TEN_PARSECS: f64 = 3.085677581491367e17;
+
+/// Kilometers in the diameter of a body of absolute magnitude 0 and geometric albedo 1
+const ZERO_MAGNITUDE_DIAMETER: f64 = 1329.0;
+
+/// Slope parameter assumed for bodies whose magnitude comes from their size and albedo
+const DEFAULT_SLOPE: f64 = 0.15;
+
+/// How a body's apparent visual magnitude depends on its distances from the Sun and the observer and its phase angle
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MagnitudeLaw {
+    /// The IAU H, G system for asteroids and other small bodies: absolute magnitude and slope parameter
+    Asteroid { h: f64, g: f64 },
+    /// Total magnitude of a comet, brightening by 2.5 × slope × log r towards the Sun
+    Comet { h: f64, slope: f64 },
+    /// Phase curves of the planets from Mallama & Hilton (2018); Saturn's includes its rings
+    Mercury,
+    Venus,
+    Earth,
+    Mars,
+    Jupiter,
+    Saturn,
+    Uranus,
+    Neptune,
+    /// The Moon's phase curve, from Allen's Astrophysical Quantities
+    Moon,
+    /// A body shining by its own light, like the Sun, from its absolute magnitude
+    Star { absolute: f64 },
+    /// The same magnitude wherever it is seen from, for stars outside the system
+    Fixed { magnitude: f64 },
+}
+
+/// Where a body is relative to the Sun and the observer
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Geometry {
+    pub sun_distance: Length,
+    pub distance: Length,
+    /// Angle at the body between the Sun and the observer
+    pub phase_angle: Angle,
+    /// Latitude of the observer above Saturn's ring plane, for Saturn's law
+    pub ring_tilt: Angle,
+}
+
+impl MagnitudeLaw {
+    /// Build an H, G law from a body's size and geometric albedo
+    pub fn from_size(radius: Length, albedo: f64) -> MagnitudeLaw {
+        let diameter = 2.0 * radius.get::<kilometer>();
+        MagnitudeLaw::Asteroid { h: 5.0 * (ZERO_MAGNITUDE_DIAMETER / (diameter * albedo.sqrt())).log10(), g: DEFAULT_SLOPE }
+    }
+
+    /// Whether the law needs the Sun's distance and the phase angle
+    pub fn needs_sun(self) -> bool {
+        !matches!(self, MagnitudeLaw::Star { .. } | MagnitudeLaw::Fixed { .. })
+    }
+
+    /// Apparent visual magnitude
+    pub fn magnitude(self, geometry: &Geometry) -> f64 {
+        let r = geometry.sun_distance.get::<astronomical_unit>();
+        let delta = geometry.distance.get::<astronomical_unit>();
+        let distances = 5.0 * (r * delta).log10();
+        let a = geometry.phase_angle.get::<degree>().abs();
+        match self {
+            MagnitudeLaw::Asteroid { h, g } => {
+                let tan = (geometry.phase_angle.get::<radian>().abs() / 2.0).tan();
+                let phi1 = (-3.33 * tan.powf(0.63)).exp();
+                let phi2 = (-1.87 * tan.powf(1.22)).exp();
+                h + distances - 2.5 * ((1.0 - g) * phi1 + g * phi2).log10()
+            },
+            MagnitudeLaw::Comet { h, slope } => h + 5.0 * delta.log10() + 2.5 * slope * r.log10(),
+            MagnitudeLaw::Mercury => {
+                distances - 0.613 + 6.328e-2 * a - 1.6336e-3 * a.powi(2) + 3.3644e-5 * a.powi(3) - 3.4265e-7 * a.powi(4)
+                    + 1.6893e-9 * a.powi(5) - 3.0334e-12 * a.powi(6)
+            },
+            MagnitudeLaw::Venus => {
+                if a < 163.7 {
+                    distances - 4.384 - 1.044e-3 * a + 3.687e-4 * a.powi(2) - 2.814e-6 * a.powi(3) + 8.938e-9 * a.powi(4)
+                }
+                else {
+                    distances + 236.05828 - 2.81914 * a + 8.39034e-3 * a.powi(2)
+                }
+            },
+            MagnitudeLaw::Earth => distances - 3.99 - 1.060e-3 * a + 2.054e-4 * a.powi(2),
+            MagnitudeLaw::Mars => {
+                if a <= 50.0 {
+                    distances - 1.601 + 2.267e-2 * a - 1.302e-4 * a.powi(2)
+                }
+                else {
+                    distances - 0.367 - 2.573e-2 * a + 3.445e-4 * a.powi(2)
+                }
+            },
+            MagnitudeLaw::Jupiter => {
+                if a <= 12.0 {
+                    distances - 9.395 - 3.7e-4 * a + 6.16e-4 * a.powi(2)
+                }
+                else {
+                    let x = a / 180.0;
+                    let curve = 1.0 - 1.507 * x - 0.363 * x.powi(2) - 0.062 * x.powi(3) + 2.809 * x.powi(4) - 1.876 * x.powi(5);
+                    distances - 9.428 - 2.5 * curve.log10()
+                }
+            },
+            MagnitudeLaw::Saturn => {
+                // The rings only count at the small phase angles seen from the Earth
+                if a <= 6.5 {
+                    let tilt = geometry.ring_tilt.get::<radian>().abs().sin();
+                    distances - 8.914 - 1.825 * tilt + 2.6e-2 * a - 0.378 * tilt * (-2.25 * a).exp()
+                }
+                else {
+                    distances - 8.94 + 2.446e-4 * a + 2.672e-4 * a.powi(2) - 1.505e-6 * a.powi(3) + 4.767e-9 * a.powi(4)
+                }
+            },
+            MagnitudeLaw::Uranus => distances - 7.110 + 6.587e-3 * a + 1.045e-4 * a.powi(2),
+            MagnitudeLaw::Neptune => distances - 7.00 + 7.944e-3 * a + 9.617e-5 * a.powi(2),
+            MagnitudeLaw::Moon => distances + 0.21 + 0.026 * a + 4.0e-9 * a.powi(4),
+            MagnitudeLaw::Star { absolute } => absolute + 5.0 * (geometry.distance.get::<meter>() / TEN_PARSECS).log10(),
+            MagnitudeLaw::Fixed { magnitude } => magnitude,
+        }
+    }
+}
+
+/// The magnitude law of an object: its own, one from its size and albedo, or a star's catalogue magnitude
+pub fn law_for(obj: &SolarSystemObject) -> Option<MagnitudeLaw> {
+    match obj {
+        SolarSystemObject::Star { f, .. } => Some(MagnitudeLaw::Fixed { magnitude: f.magnitude() }),
+        _ => obj.magnitude_law().or_else(|| {
+            let (radius, albedo) = (obj.get_radius(), obj.get_albedo());
+            (radius.get::<meter>() > 0.0 && albedo > 0.0).then(|| MagnitudeLaw::from_size(radius, albedo))
+        }),
+    }
+}
+
+/// Latitude of a position seen from the Earth above Saturn's ring plane (Meeus ch. 45)
+pub fn ring_tilt(position: Cartesian<EclipticJ2000>, time: Time) -> Angle {
+    let t = julian_centuries(time);
+    let inclination = Angle::new::<degree>(28.075216 - 0.012998 * t + 0.000004 * t * t);
+    let node = Angle::new::<degree>(169.508470 + 1.394681 * t + 0.000412 * t * t);
+    let ecliptic = position.to::<EclipticOfDate>(time).spherical();
+    let sin = inclination.sin().value * ecliptic.latitude.cos().value * (ecliptic.longitude - node).sin().value
+        - inclination.cos().value * ecliptic.latitude.sin().value;
+    Angle::new::<radian>(sin.asin())
+}
+
+/// How a body looks from the zodiac center
+#[derive(Debug, Clone, PartialEq)]
+pub struct Visibility {
+    pub distance: Length,
+    pub angular_diameter: Angle,
+    /// Angle from the Sun, and whether the body is east of it
+    pub elongation: Option<(Angle, bool)>,
+    pub phase_angle: Option<Angle>,
+    /// Fraction of the disk that is lit
+    pub illuminated: Option<f64>,
+    pub magnitude: Option<f64>,
+    /// Tilt of Saturn's rings towards the observer, positive when their north face is seen
+    pub ring_tilt: Option<Angle>,
+}
+
+/// Distance, size, phase and brightness of an object seen from the zodiac center
+pub fn visibility(solar_system: &SolarSystem, obj: &SolarSystemObject, time: Time) -> Visibility {
+    let position = solar_system.observed_position(obj, time);
+    let distance = position.norm();
+    let angular_diameter = 2.0 * Angle::new::<radian>((obj.get_radius() / distance).value.min(1.0).asin());
+    let law = law_for(obj);
+    let ring_tilt = (law == Some(MagnitudeLaw::Saturn)).then(|| ring_tilt(position, time));
+    let sun = solar_system.get("Sun").filter(|&sun| !std::ptr::eq(sun, obj));
+    let sun_position = sun.map(|sun| solar_system.observed_position(sun, time));
+    let elongation = sun_position.map(|sun_position| {
+        let longitude = |position: Cartesian<EclipticJ2000>| position.to::<EclipticOfDate>(time).spherical().longitude;
+        (position.separation(&sun_position), (longitude(position) - longitude(sun_position)).sin().value > 0.0)
+    });
+    // Stars, empty points and the Sun itself have no phase
+    let lit = sun_position.filter(|_| !matches!(obj, SolarSystemObject::Star { .. } | SolarSystemObject::Lagrange { .. }))
+        .map(|sun_position| ((sun_position - position).separation(&-position), (sun_position - position).norm()));
+    let magnitude = law.and_then(|law| {
+        let geometry = match lit {
+            Some((phase_angle, sun_distance)) => Geometry { sun_distance, distance, phase_angle, ring_tilt: ring_tilt.unwrap_or_default() },
+            None if !law.needs_sun() => Geometry { sun_distance: distance, distance, phase_angle: Angle::default(), ring_tilt: Angle::default() },
+            None => return None,
+        };
+        Some(law.magnitude(&geometry))
+    });
+    Visibility {
+        distance,
+        angular_diameter,
+        elongation,
+        phase_angle: lit.map(|(phase_angle, _)| phase_angle),
+        illuminated: lit.map(|(phase_angle, _)| (1.0 + phase_angle.get::<radian>().cos()) / 2.0),
+        magnitude,
+        ring_tilt,
+    }
+}
@@ -0,0 +1,168 @@
+use std::rc::Rc;
+use std::f64::consts::TAU;
+use uom::si::angle::{degree, radian};
+use uom::si::angular_velocity::radian_per_second;
+use uom::si::f64::*;
+use uom::si::length::meter;
+use uom::si::time::{day, day_sidereal};
+
+use crate::frames::{Cartesian, EclipticJ2000, Equinox, Spherical};
+use crate::precession::mean_obliquity;
+use crate::observer::Observer;
+use crate::orbitor::{normalize, Locatable, Orbitor, SolarSystemObject};
+
+/// Distance from the center at which calculated points are drawn, roughly that of the Moon
+const DISPLAY_DISTANCE: f64 = 384400000.0;
+
+/// A point that has a zodiac position but no body: lunar nodes and apsides, Arabic parts, chart angles.
+/// All longitudes are geocentric, measured from the center that the Moon orbits, in the J2000 ecliptic.
+pub enum CalculatedPoint {
+    /// Ascending node of the Moon's mean orbit
+    MeanNode { moon: Rc<SolarSystemObject> },
+    /// Ascending node corrected by the main solar perturbations of the lunar orbit (Meeus ch. 47)
+    TrueNode { moon: Rc<SolarSystemObject> },
+    /// Apogee of the Moon's mean orbit, a.k.a. Black Moon Lilith
+    MeanApogee { moon: Rc<SolarSystemObject> },
+    /// Ascendant + Moon - Sun by day, Ascendant + Sun - Moon by night
+    PartOfFortune { sun: Rc<SolarSystemObject>, moon: Rc<SolarSystemObject>, observer: Observer },
+    /// Western intersection of the prime vertical with the ecliptic
+    Vertex { center: Rc<SolarSystemObject>, observer: Observer },
+}
+
+impl CalculatedPoint {
+    fn lunar_orbit(moon: &SolarSystemObject) -> &Orbitor {
+        moon.orbitor().expect("lunar points should be built from an orbiting Moon")
+    }
+
+    /// The object the point is seen from
+    pub fn center(&self) -> &SolarSystemObject {
+        match self {
+            Self::MeanNode { moon } | Self::TrueNode { moon } | Self::MeanApogee { moon } | Self::PartOfFortune { moon, .. } => {
+                Self::lunar_orbit(moon).parent()
+            },
+            Self::Vertex { center, .. } => center,
+        }
+    }
+
+    /// Ecliptic longitude of the point as seen from its center
+    pub fn longitude(&self, time: Time) -> Angle {
+        match self {
+            Self::MeanNode { moon } => Self::lunar_orbit(moon).lan(time),
+            Self::TrueNode { moon } => true_node(Self::lunar_orbit(moon), time),
+            Self::MeanApogee { moon } => {
+                let orbit = Self::lunar_orbit(moon);
+                normalize(orbit.lan(time) + orbit.aop(time) + Angle::new::<degree>(180.0))
+            },
+            Self::PartOfFortune { sun, moon, observer } => {
+                // The ascendant comes from sidereal time, so it is measured from the equinox of date
+                let center = self.center();
+                let asc = ascendant(observer.local_sidereal_time(time), observer.latitude, mean_obliquity(time));
+                let sun_lon = Equinox::Date.longitude(sun.position_from(center, time), time);
+                let moon_lon = Equinox::Date.longitude(moon.position_from(center, time), time);
+                let fortune = if is_above_horizon(sun_lon, asc) {
+                    asc + moon_lon - sun_lon
+                }
+                else {
+                    asc + sun_lon - moon_lon
+                };
+                Equinox::Date.to_j2000(fortune, time)
+            },
+            Self::Vertex { observer, .. } => {
+                let vertex = vertex(observer.local_sidereal_time(time), observer.latitude, mean_obliquity(time));
+                Equinox::Date.to_j2000(vertex, time)
+            },
+        }
+    }
+
+    /// Time for the point to make a full circuit of the zodiac
+    pub fn period(&self) -> Time {
+        let rate = match self {
+            Self::MeanNode { moon } | Self::TrueNode { moon } => Self::lunar_orbit(moon).lan_rate(),
+            Self::MeanApogee { moon } => {
+                let orbit = Self::lunar_orbit(moon);
+                orbit.lan_rate() + orbit.aop_rate()
+            },
+            Self::PartOfFortune { .. } | Self::Vertex { .. } => return Time::new::<day_sidereal>(1.0),
+        };
+        let rate = rate.get::<radian_per_second>().abs();
+        if rate == 0.0 {
+            Time::new::<day>(1.0)
+        }
+        else {
+            Time::new::<uom::si::time::second>(TAU / rate)
+        }
+    }
+}
+
+/// Mean node plus the largest periodic terms, with the mean arguments taken from the Moon's and Earth's elements
+fn true_node(moon: &Orbitor, time: Time) -> Angle {
+    let node = moon.lan(time);
+    let Some(earth) = moon.parent().orbitor() else {
+        return node;
+    };
+    let moon_lon = moon.mean_longitude(time);
+    let sun_lon = earth.mean_longitude(time) + Angle::new::<degree>(180.0);
+    let d = (moon_lon - sun_lon).get::<radian>();
+    let f = (moon_lon - node).get::<radian>();
+    let m = earth.current_mean_anomaly(time).get::<radian>();
+    let m_moon = moon.current_mean_anomaly(time).get::<radian>();
+    let correction = -1.4979 * (2.0 * (d - f)).sin()
+        - 0.1500 * m.sin()
+        - 0.1226 * (2.0 * d).sin()
+        + 0.1176 * (2.0 * f).sin()
+        + 0.0801 * (2.0 * (m_moon - f)).sin();
+    normalize(node + Angle::new::<degree>(correction))
+}
+
+/// Ecliptic longitude rising on the eastern horizon for a given RAMC, latitude and obliquity
+pub fn ascendant(ramc: Angle, latitude: Angle, obliquity: Angle) -> Angle {
+    let y = ramc.cos().value;
+    let x = -(ramc.sin().value * obliquity.cos().value + latitude.tan().value * obliquity.sin().value);
+    normalize(Angle::new::<radian>(y.atan2(x)))
+}
+
+/// The Vertex is the ascendant of the opposite meridian at the co-latitude
+pub fn vertex(ramc: Angle, latitude: Angle, obliquity: Angle) -> Angle {
+    let colatitude = if latitude.get::<degree>() >= 0.0 {
+        Angle::new::<degree>(90.0) - latitude
+    }
+    else {
+        Angle::new::<degree>(-90.0) - latitude
+    };
+    ascendant(ramc + Angle::new::<degree>(180.0), colatitude, obliquity)
+}
+
+/// Whether an ecliptic longitude lies in the half of the chart above the horizon (houses 7 through 12)
+fn is_above_horizon(longitude: Angle, ascendant: Angle) -> bool {
+    normalize(longitude - ascendant).get::<degree>() > 180.0
+}
+
+impl Locatable for CalculatedPoint {
+    fn position(&self, time: Time) -> Cartesian<EclipticJ2000> {
+        let offset = Cartesian::from_spherical(Spherical {
+            longitude: self.longitude(time),
+            latitude: Angle::new::<radian>(0.0),
+            distance: Length::new::<meter>(DISPLAY_DISTANCE),
+        });
+        self.center().position(time) + offset
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orbitor::{jd_to_internal, SolarSystem};
+
+    /// 1992 April 12, 0h TD (Meeus example 47.a): formula 47.7 with the example's arguments D = 113.842304°,
+    /// M = 97.643514°, M' = 5.150833° and F = 219.889721°, and the mean node 274.4007°, gives 273.5875° on the
+    /// equinox of date
+    #[test]
+    fn true_node_matches_meeus() {
+        let solar_system = SolarSystem::new_default();
+        let moon = solar_system.get("Moon").unwrap().orbitor().unwrap();
+        let time = jd_to_internal(2448724.5);
+        let expected = Equinox::Date.to_j2000(Angle::new::<degree>(273.5875), time);
+        let node = true_node(moon, time);
+        assert!((node - expected).get::<degree>().abs() < 0.01, "true node {}", node.get::<degree>());
+    }
+}
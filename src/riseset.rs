@@ -0,0 +1,149 @@
+use std::f64::consts::PI;
+use uom::si::angle::{degree, radian};
+use uom::si::f64::*;
+use uom::si::time::second;
+
+use crate::frames::EquatorialOfDate;
+use crate::horizon::Horizontal;
+use crate::orbitor::{SolarSystem, SolarSystemObject};
+use crate::search::bisect;
+
+/// Spacing of the altitude samples searched for crossings; short enough that the Moon can't rise and set in between
+const STEP: f64 = 600.0;
+
+/// Refraction at the horizon conventionally used for rising and setting, in arcminutes
+const HORIZON_REFRACTION: f64 = 34.0;
+
+/// Where a body's altitude is measured from when looking for it crossing the horizon
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Horizon {
+    /// Rising and setting: the upper limb of the body (which only matters for the Sun and Moon) on the horizon,
+    /// allowing for refraction
+    Standard,
+    /// The center of the body at a true (unrefracted) altitude, as for twilight
+    Altitude(Angle),
+}
+
+/// Times a body goes up and down through a horizon
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Crossings {
+    pub rise: Option<Time>,
+    pub set: Option<Time>,
+    /// Whether the body was above the horizon at the start, so when there are no crossings it was up or down throughout
+    pub up_at_start: bool,
+}
+
+impl Crossings {
+    pub fn always_above(&self) -> bool {
+        self.rise.is_none() && self.set.is_none() && self.up_at_start
+    }
+
+    pub fn always_below(&self) -> bool {
+        self.rise.is_none() && self.set.is_none() && !self.up_at_start
+    }
+
+    /// How long the body is above the horizon between `start` and `end`
+    pub fn time_above(&self, start: Time, end: Time) -> Time {
+        match (self.up_at_start, self.rise, self.set) {
+            (true, rise, set) => set.unwrap_or(end) - start + rise.map_or(Time::new::<second>(0.0), |rise| end - rise),
+            (false, Some(rise), set) => set.filter(|set| *set > rise).unwrap_or(end) - rise,
+            (false, None, _) => Time::new::<second>(0.0),
+        }
+    }
+}
+
+/// Rising, upper transit and setting of a body between two times, usually a local day
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RiseTransitSet {
+    pub crossings: Crossings,
+    /// Time of the transit and the (refracted) altitude then
+    pub transit: Option<(Time, Angle)>,
+}
+
+/// Find when a body rises, transits and sets for the system's observer between `start` and `end`,
+/// allowing for the Moon's parallax. If a body rises or sets more than once, the first is kept.
+pub fn rise_transit_set(solar_system: &SolarSystem, obj: &SolarSystemObject, start: Time, end: Time) -> RiseTransitSet {
+    RiseTransitSet {
+        crossings: crossings(solar_system, obj, start, end, Horizon::Standard),
+        transit: transit(solar_system, obj, start, end),
+    }
+}
+
+/// Find the first times between `start` and `end` that a body goes up and down through a horizon
+pub fn crossings(solar_system: &SolarSystem, obj: &SolarSystemObject, start: Time, end: Time, horizon: Horizon) -> Crossings {
+    let height = |time: Time| altitude_above_horizon(solar_system, obj, time, horizon);
+    let mut h0 = height(start);
+    let mut crossings = Crossings { rise: None, set: None, up_at_start: h0 > 0.0 };
+    for (t0, t1) in steps(start, end) {
+        let h1 = height(t1);
+        if h0 <= 0.0 && h1 > 0.0 && crossings.rise.is_none() {
+            crossings.rise = Some(bisect(height, t0, t1));
+        }
+        if h0 > 0.0 && h1 <= 0.0 && crossings.set.is_none() {
+            crossings.set = Some(bisect(height, t0, t1));
+        }
+        h0 = h1;
+    }
+    crossings
+}
+
+/// Find the first upper transit between `start` and `end`, and the refracted altitude then
+pub fn transit(solar_system: &SolarSystem, obj: &SolarSystemObject, start: Time, end: Time) -> Option<(Time, Angle)> {
+    let hour_angle = |time: Time| hour_angle(solar_system, obj, time);
+    let mut ha0 = hour_angle(start);
+    for (t0, t1) in steps(start, end) {
+        let ha1 = hour_angle(t1);
+        // The hour angle jumps from +180° to -180° at lower transit, which is not a crossing
+        if ha0 < 0.0 && ha1 >= 0.0 && ha1 - ha0 < PI {
+            let transit = bisect(hour_angle, t0, t1);
+            return Some((transit, horizontal(solar_system, obj, transit).0.refracted().altitude));
+        }
+        ha0 = ha1;
+    }
+    None
+}
+
+/// Consecutive sample times from `start` to `end`
+fn steps(start: Time, end: Time) -> impl Iterator<Item = (Time, Time)> {
+    let step = Time::new::<second>(STEP);
+    let count = ((end - start).get::<second>() / STEP).ceil().max(0.0) as usize;
+    (0..count).map(move |i| {
+        let t0 = start + step * i as f64;
+        let t1 = if t0 + step > end { end } else { t0 + step };
+        (t0, t1)
+    })
+}
+
+/// Altitude and azimuth of a body for the system's observer, and its distance
+fn horizontal(solar_system: &SolarSystem, obj: &SolarSystemObject, time: Time) -> (Horizontal, Length) {
+    let position = solar_system.topocentric_position(obj, time).to::<EquatorialOfDate>(time);
+    (Horizontal::from_equatorial(position, solar_system.observer(), time), position.norm())
+}
+
+/// Altitude of a body above a horizon, in radians
+fn altitude_above_horizon(solar_system: &SolarSystem, obj: &SolarSystemObject, time: Time, horizon: Horizon) -> f64 {
+    let (horizontal, distance) = horizontal(solar_system, obj, time);
+    let altitude = match horizon {
+        Horizon::Standard => standard_altitude(obj, distance),
+        Horizon::Altitude(altitude) => altitude,
+    };
+    (horizontal.altitude - altitude).get::<radian>()
+}
+
+/// Altitude of the center of a body when it rises or sets
+fn standard_altitude(obj: &SolarSystemObject, distance: Length) -> Angle {
+    if matches!(obj, SolarSystemObject::Calculated { .. }) {
+        return Angle::new::<radian>(0.0);
+    }
+    let refraction = Angle::new::<degree>(HORIZON_REFRACTION / 60.0);
+    let semidiameter = (obj.get_radius() / distance).value.min(1.0).asin();
+    -refraction - Angle::new::<radian>(semidiameter)
+}
+
+/// Local hour angle of a body in radians, between -π and π
+fn hour_angle(solar_system: &SolarSystem, obj: &SolarSystemObject, time: Time) -> f64 {
+    let position = solar_system.topocentric_position(obj, time).to::<EquatorialOfDate>(time);
+    let sidereal_time = solar_system.observer().local_apparent_sidereal_time(time);
+    let hour_angle = (sidereal_time - position.spherical().longitude).get::<radian>();
+    (hour_angle + PI).rem_euclid(2.0 * PI) - PI
+}
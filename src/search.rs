@@ -0,0 +1,63 @@
+use uom::si::f64::*;
+use uom::si::time::second;
+
+/// Times are refined to this many seconds
+const TOLERANCE: f64 = 1.0;
+
+/// Narrow down where a function goes from one sign to the other between two times
+pub fn bisect(f: impl Fn(Time) -> f64, mut t0: Time, mut t1: Time) -> Time {
+    let rising = f(t0) <= 0.0;
+    while (t1 - t0).get::<second>() > TOLERANCE {
+        let mid = t0 + (t1 - t0) / 2.0;
+        if (f(mid) <= 0.0) == rising {
+            t0 = mid;
+        }
+        else {
+            t1 = mid;
+        }
+    }
+    t0 + (t1 - t0) / 2.0
+}
+
+/// Golden-section search for the time a function with a single minimum between two times is least
+pub fn minimize(f: impl Fn(Time) -> f64, mut t0: Time, mut t1: Time) -> Time {
+    let ratio = (5f64.sqrt() - 1.0) / 2.0;
+    let mut a = t1 - (t1 - t0) * ratio;
+    let mut b = t0 + (t1 - t0) * ratio;
+    let (mut fa, mut fb) = (f(a), f(b));
+    while (t1 - t0).get::<second>() > TOLERANCE {
+        if fa < fb {
+            t1 = b;
+            b = a;
+            fb = fa;
+            a = t1 - (t1 - t0) * ratio;
+            fa = f(a);
+        }
+        else {
+            t0 = a;
+            a = b;
+            fa = fb;
+            b = t0 + (t1 - t0) * ratio;
+            fb = f(b);
+        }
+    }
+    t0 + (t1 - t0) / 2.0
+}
+
+/// Times within a window before and after a moment when a function, negative at that moment, goes back through zero
+pub fn contacts(greatest: Time, window: Time, f: impl Fn(Time) -> f64) -> (Option<Time>, Option<Time>) {
+    if f(greatest) >= 0.0 {
+        return (None, None);
+    }
+    let before = greatest - window;
+    let after = greatest + window;
+    (
+        (f(before) > 0.0).then(|| bisect(&f, before, greatest)),
+        (f(after) > 0.0).then(|| bisect(&f, greatest, after)),
+    )
+}
+
+/// The named times that happen, in the order given
+pub fn happening<const N: usize>(times: [(&'static str, Option<Time>); N]) -> Vec<(&'static str, Time)> {
+    times.into_iter().filter_map(|(name, time)| time.map(|time| (name, time))).collect()
+}
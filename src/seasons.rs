@@ -0,0 +1,94 @@
+use uom::si::angle::degree;
+use uom::si::f64::*;
+use uom::si::time::second;
+
+use crate::calendar::CalendarDate;
+use crate::frames::EclipticOfDate;
+use crate::orbitor::{jd_to_internal, SolarSystem, SolarSystemObject};
+
+/// Days the Sun takes to move a radian of longitude, near enough to correct a guess (Meeus ch. 27 uses 58)
+const DAYS_PER_RADIAN: f64 = 58.0;
+
+/// Times are refined to this many seconds
+const TOLERANCE: f64 = 0.1;
+
+/// The equinoxes and solstices, when the Sun's apparent longitude is 0°, 90°, 180° or 270°
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Season {
+    MarchEquinox,
+    JuneSolstice,
+    SeptemberEquinox,
+    DecemberSolstice,
+}
+
+impl Season {
+    pub const ALL: [Season; 4] = [Season::MarchEquinox, Season::JuneSolstice, Season::SeptemberEquinox, Season::DecemberSolstice];
+
+    pub fn longitude(self) -> Angle {
+        Angle::new::<degree>(match self {
+            Season::MarchEquinox => 0.0,
+            Season::JuneSolstice => 90.0,
+            Season::SeptemberEquinox => 180.0,
+            Season::DecemberSolstice => 270.0,
+        })
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Season::MarchEquinox => "March equinox",
+            Season::JuneSolstice => "June solstice",
+            Season::SeptemberEquinox => "September equinox",
+            Season::DecemberSolstice => "December solstice",
+        }
+    }
+
+    /// The astronomical season that begins, in the northern or southern hemisphere
+    pub fn begins(self, northern: bool) -> &'static str {
+        const NAMES: [&str; 4] = ["spring", "summer", "autumn", "winter"];
+        let index = self as usize + if northern { 0 } else { 2 };
+        NAMES[index % 4]
+    }
+
+    /// Month and day it falls on in the Gregorian calendar, give or take a couple of days
+    fn usual_date(self) -> (u8, u8) {
+        match self {
+            Season::MarchEquinox => (3, 20),
+            Season::JuneSolstice => (6, 21),
+            Season::SeptemberEquinox => (9, 22),
+            Season::DecemberSolstice => (12, 21),
+        }
+    }
+}
+
+/// When the Sun reaches the longitude of an equinox or solstice in a year, seen from the zodiac center
+pub fn season_in(solar_system: &SolarSystem, season: Season, year: i32) -> Result<Time, String> {
+    let sun = solar_system.get("Sun").ok_or("Seasons need an object named Sun")?;
+    let (month, day) = season.usual_date();
+    let guess = CalendarDate { year, month, day, hour: 12, minute: 0, second: 0.0 }.julian_date();
+    Ok(refine(solar_system, sun, season.longitude(), jd_to_internal(guess)))
+}
+
+/// Equinoxes and solstices of a range of years, in order
+pub fn seasons_between(solar_system: &SolarSystem, first_year: i32, last_year: i32) -> Result<Vec<(Season, Time)>, String> {
+    let mut seasons = Vec::new();
+    for year in first_year..=last_year {
+        for season in Season::ALL {
+            seasons.push((season, season_in(solar_system, season, year)?));
+        }
+    }
+    Ok(seasons)
+}
+
+/// Step towards when the Sun's apparent longitude of date reaches a value, by how far it has to go (Meeus ch. 27).
+/// The Sun's longitude only changes by a few percent in speed, so each step cuts the error a hundredfold.
+fn refine(solar_system: &SolarSystem, sun: &SolarSystemObject, target: Angle, mut time: Time) -> Time {
+    for _ in 0..20 {
+        let longitude = solar_system.observed_position(sun, time).to::<EclipticOfDate>(time).spherical().longitude;
+        let correction = Time::new::<second>(DAYS_PER_RADIAN * 86400.0 * (target - longitude).sin().value);
+        time += correction;
+        if correction.get::<second>().abs() < TOLERANCE {
+            break;
+        }
+    }
+    time
+}
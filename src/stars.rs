@@ -0,0 +1,108 @@
+use plotters::style::RGBColor;
+use uom::si::angle::{degree, radian};
+use uom::si::f64::*;
+use uom::si::length::meter;
+use uom::si::time::{day, second};
+
+use crate::frames::{Cartesian, EclipticJ2000, EquatorialJ2000, Spherical};
+use crate::orbitor::{Locatable, SolarSystemObject};
+
+/// Meters in a parsec
+const PARSEC: f64 = 3.085677581491367e16;
+
+/// Stars without a known parallax are put this many parsecs away, which is as good as infinitely far
+const UNKNOWN_DISTANCE: f64 = 1e6;
+
+/// Bright stars close enough to the ecliptic for the Moon to pass in front of them:
+/// name, right ascension and declination (ICRS, epoch J2000) in degrees, proper motion in right ascension
+/// (times the cosine of declination) and declination in milliarcseconds a year, parallax in milliarcseconds,
+/// and visual magnitude (Hipparcos, new reduction)
+pub const BRIGHT_STARS: [(&str, f64, f64, f64, f64, f64, f64); 5] = [
+    ("Alcyone", 56.87115240, 24.10513625, 19.34, -43.67, 8.09, 2.87),
+    ("Aldebaran", 68.98016279, 16.50930235, 63.45, -188.94, 48.94, 0.86),
+    ("Regulus", 152.09296244, 11.96720878, -248.73, 5.59, 41.13, 1.40),
+    ("Spica", 201.29824736, -11.16131949, -42.35, -30.67, 13.06, 0.97),
+    ("Antares", 247.35191542, -26.43200261, -12.11, -23.30, 5.89, 1.06),
+];
+
+/// A star far outside the system, at a catalogue position moved along by its proper motion.
+/// Positions are as seen from the system's root, so light time is already in them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FixedStar {
+    right_ascension: Angle,
+    declination: Angle,
+    /// Milliarcseconds a year, in right ascension times the cosine of declination, and in declination
+    proper_motion: [f64; 2],
+    /// Milliarcseconds
+    parallax: f64,
+    magnitude: f64,
+}
+
+impl FixedStar {
+    pub fn new(right_ascension: Angle, declination: Angle) -> FixedStar {
+        FixedStar { right_ascension, declination, proper_motion: [0.0, 0.0], parallax: 0.0, magnitude: 0.0 }
+    }
+
+    pub fn with_proper_motion(mut self, right_ascension: f64, declination: f64) -> FixedStar {
+        self.proper_motion = [right_ascension, declination];
+        self
+    }
+
+    pub fn with_parallax(mut self, parallax: f64) -> FixedStar {
+        self.parallax = parallax;
+        self
+    }
+
+    pub fn with_magnitude(mut self, magnitude: f64) -> FixedStar {
+        self.magnitude = magnitude;
+        self
+    }
+
+    pub fn right_ascension(&self) -> Angle {
+        self.right_ascension
+    }
+
+    pub fn declination(&self) -> Angle {
+        self.declination
+    }
+
+    pub fn proper_motion(&self) -> [f64; 2] {
+        self.proper_motion
+    }
+
+    pub fn parallax(&self) -> f64 {
+        self.parallax
+    }
+
+    pub fn magnitude(&self) -> f64 {
+        self.magnitude
+    }
+
+    /// Distance from the parallax
+    pub fn distance(&self) -> Length {
+        let parsecs = if self.parallax > 0.0 { 1000.0 / self.parallax } else { UNKNOWN_DISTANCE };
+        Length::new::<meter>(parsecs * PARSEC)
+    }
+}
+
+impl Locatable for FixedStar {
+    fn position(&self, time: Time) -> Cartesian<EclipticJ2000> {
+        let years = time.get::<day>() / 365.25;
+        let [ra_motion, dec_motion] = self.proper_motion.map(|mas| Angle::new::<degree>(mas * years / 3.6e6));
+        let declination = self.declination + dec_motion;
+        let right_ascension = self.right_ascension + Angle::new::<radian>(ra_motion.get::<radian>() / declination.cos().value);
+        Cartesian::<EquatorialJ2000>::from_spherical(Spherical { longitude: right_ascension, latitude: declination, distance: self.distance() })
+            .to(Time::new::<second>(0.0))
+    }
+}
+
+/// The stars of `BRIGHT_STARS`, ready to add to a system
+pub fn bright_stars() -> Vec<SolarSystemObject> {
+    BRIGHT_STARS.iter().map(|&(name, ra, dec, pm_ra, pm_dec, parallax, magnitude)| {
+        let star = FixedStar::new(Angle::new::<degree>(ra), Angle::new::<degree>(dec))
+            .with_proper_motion(pm_ra, pm_dec)
+            .with_parallax(parallax)
+            .with_magnitude(magnitude);
+        SolarSystemObject::new_star(name, RGBColor(255, 255, 224), star)
+    }).collect()
+}
@@ -0,0 +1,272 @@
+use std::time::SystemTime;
+use time::{
+    format_description::well_known::{Iso8601, Rfc2822, Rfc3339}, parsing::Parsable, OffsetDateTime
+};
+use time_tz::Tz;
+use uom::si::{f64::Time, time::second};
+
+use crate::calendar::{self, CalendarDate, MJD_OFFSET};
+use crate::orbitor::jd_to_internal;
+use crate::timescales::{utc_jd_to_tt, utc_to_tt};
+use crate::timezone::{from_wall, midnight, wall};
+
+/// A time from the command line: a starting point, then offsets from it applied in order.
+/// Dates written without an offset, and anything that depends on the calendar, are worked out in the output time zone,
+/// which isn't known until all the arguments are parsed.
+#[derive(Debug, Clone)]
+pub struct TimeInput {
+    base: Base,
+    offsets: Vec<(f64, Unit)>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Base {
+    /// An instant, in TT
+    Instant(Time),
+    /// A Julian date read off a clock in the output time zone
+    Wall(f64),
+    Now,
+    /// Midnight some days from today, plus a time of day in seconds
+    Day { days: i64, time_of_day: f64 },
+    /// The next or last day that is a weekday (0 is Sunday), plus a time of day in seconds
+    Weekday { weekday: i64, forward: bool, time_of_day: f64 },
+    /// The start of the period containing today, or the one `shift` periods later
+    StartOf { period: Unit, shift: i32 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Unit {
+    Second,
+    Minute,
+    Hour,
+    Day,
+    Week,
+    Month,
+    Year,
+}
+
+/// Bare numbers are read as Julian dates only with seven digits, from 1976 BC to AD 22666
+const BARE_JD_RANGE: std::ops::Range<f64> = 1_000_000.0..10_000_000.0;
+
+const WEEKDAYS: [&str; 7] = ["sunday", "monday", "tuesday", "wednesday", "thursday", "friday", "saturday"];
+
+impl Unit {
+    fn parse(s: &str) -> Result<Unit, String> {
+        match s.to_lowercase().as_str() {
+            "s" | "sec" | "secs" | "second" | "seconds" => Ok(Unit::Second),
+            "min" | "mins" | "minute" | "minutes" => Ok(Unit::Minute),
+            "h" | "hr" | "hrs" | "hour" | "hours" => Ok(Unit::Hour),
+            "d" | "day" | "days" => Ok(Unit::Day),
+            "w" | "wk" | "week" | "weeks" => Ok(Unit::Week),
+            "mo" | "month" | "months" => Ok(Unit::Month),
+            "y" | "yr" | "year" | "years" => Ok(Unit::Year),
+            "m" => Err("Ambiguous unit \"m\": use min for minutes or mo for months".into()),
+            _ => Err(format!("Unknown unit {s:?}, expected one of s, min, h, d, w, mo, y")),
+        }
+    }
+
+    /// Move a time on by an amount of this unit. Days and longer follow the calendar and the clock in the zone,
+    /// so `+1d` keeps the time of day across a change to daylight saving time, and `+1mo` from Jan 31 is Feb 28 or 29.
+    fn add(self, time: Time, amount: f64, zone: &Tz) -> Result<Time, String> {
+        let seconds = |s: f64| Ok(time + Time::new::<second>(amount * s));
+        match self {
+            Unit::Second => seconds(1.0),
+            Unit::Minute => seconds(60.0),
+            Unit::Hour => seconds(3600.0),
+            Unit::Day => from_wall(zone, wall(zone, time) + amount),
+            Unit::Week => from_wall(zone, wall(zone, time) + 7.0 * amount),
+            Unit::Month | Unit::Year => {
+                let months = if self == Unit::Year { 12 * amount as i32 } else { amount as i32 };
+                let date = CalendarDate::from_julian_date(wall(zone, time)).add_months(months);
+                from_wall(zone, date.julian_date())
+            },
+        }
+    }
+}
+
+impl TimeInput {
+    pub fn resolve(&self, zone: &Tz) -> Result<Time, String> {
+        let now = || utc_to_tt(SystemTime::now().into());
+        let today = || midnight(wall(zone, now()));
+        let mut time = match self.base {
+            Base::Instant(time) => time,
+            Base::Wall(jd) => from_wall(zone, jd)?,
+            Base::Now => now(),
+            Base::Day { days, time_of_day } => from_wall(zone, today() + days as f64 + time_of_day / 86400.0)?,
+            Base::Weekday { weekday, forward, time_of_day } => {
+                let today = today();
+                let days = if forward {
+                    (weekday - weekday_of(today) - 1).rem_euclid(7) + 1
+                }
+                else {
+                    -((weekday_of(today) - weekday - 1).rem_euclid(7) + 1)
+                };
+                from_wall(zone, today + days as f64 + time_of_day / 86400.0)?
+            },
+            Base::StartOf { period, shift } => {
+                let today = today();
+                let date = CalendarDate::from_julian_date(today);
+                let start = match period {
+                    Unit::Week => today - (weekday_of(today) + 6).rem_euclid(7) as f64 + 7.0 * shift as f64,
+                    Unit::Month => CalendarDate { day: 1, ..date }.add_months(shift).julian_date(),
+                    Unit::Year => CalendarDate { month: 1, day: 1, ..date }.add_months(12 * shift).julian_date(),
+                    _ => today + shift as f64,
+                };
+                from_wall(zone, start)?
+            },
+        };
+        for &(amount, unit) in &self.offsets {
+            time = unit.add(time, amount, zone)?;
+        }
+        Ok(time)
+    }
+}
+
+/// Day of the week of a Julian date, 0 for Sunday
+fn weekday_of(jd: f64) -> i64 {
+    ((jd + 0.5).floor() as i64 + 1).rem_euclid(7)
+}
+
+/// Parse a time, which can be absolute (`2024-03-01`, `JD2460371.5`), relative to today (`tomorrow 09:00`,
+/// `next monday`, `start of next month`), or `now`, and be followed by offsets like `+3d` or `-6h`
+pub fn parse_time(time_str: &str) -> Result<TimeInput, String> {
+    let mut rest = time_str.trim();
+    let mut offsets = Vec::new();
+    while let Some((before, amount, unit)) = split_trailing_offset(rest)? {
+        offsets.push((amount, unit));
+        rest = before;
+    }
+    offsets.reverse();
+    let base = if rest.is_empty() && !offsets.is_empty() { Base::Now } else { parse_base(rest)? };
+    Ok(TimeInput { base, offsets })
+}
+
+/// Split an offset like `+3d`, `-6h` or `+ 2 weeks` from the end of a time
+fn split_trailing_offset(s: &str) -> Result<Option<(&str, f64, Unit)>, String> {
+    let s = s.trim_end();
+    let unit_start = s.trim_end_matches(|c: char| c.is_ascii_alphabetic()).len();
+    let head = s[..unit_start].trim_end();
+    let number_start = head.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.').len();
+    let before = head[..number_start].trim_end();
+    let sign = match before.chars().last() {
+        _ if unit_start == s.len() || number_start == head.len() => return Ok(None),
+        Some('+') => 1.0,
+        Some('-') => -1.0,
+        _ => return Ok(None),
+    };
+    let number = &head[number_start..];
+    let amount: f64 = number.parse().map_err(|_| format!("Invalid offset amount {number:?}"))?;
+    let unit = Unit::parse(&s[unit_start..])?;
+    if matches!(unit, Unit::Month | Unit::Year) && amount.fract() != 0.0 {
+        return Err(format!("Months and years must be whole numbers, not {number}"));
+    }
+    Ok(Some((before[..before.len() - 1].trim_end(), sign * amount, unit)))
+}
+
+fn parse_base(s: &str) -> Result<Base, String> {
+    let lower = s.to_lowercase();
+    let words: Vec<&str> = lower.split_whitespace().collect();
+    match words.as_slice() {
+        ["now"] => Ok(Base::Now),
+        [day @ ("today" | "tomorrow" | "yesterday"), time_of_day @ ..] => {
+            let days = match *day {
+                "tomorrow" => 1,
+                "yesterday" => -1,
+                _ => 0,
+            };
+            Ok(Base::Day { days, time_of_day: parse_time_of_day(time_of_day)? })
+        },
+        [direction @ ("next" | "last"), day, time_of_day @ ..] if !matches!(*day, "day" | "week" | "month" | "year") => {
+            let weekday = WEEKDAYS.iter().position(|w| w == day || w[..3] == **day)
+                .ok_or(format!("Unknown weekday {day:?}"))?;
+            Ok(Base::Weekday { weekday: weekday as i64, forward: *direction == "next", time_of_day: parse_time_of_day(time_of_day)? })
+        },
+        ["start", "of", which, period] => {
+            let shift = match *which {
+                "this" => 0,
+                "next" => 1,
+                "last" => -1,
+                _ => return Err(format!("Expected this, next or last in {s:?}")),
+            };
+            let period = match *period {
+                "day" => Unit::Day,
+                "week" => Unit::Week,
+                "month" => Unit::Month,
+                "year" => Unit::Year,
+                _ => return Err(format!("Expected day, week, month or year in {s:?}")),
+            };
+            Ok(Base::StartOf { period, shift })
+        },
+        _ => parse_absolute(s),
+    }
+}
+
+/// A time of day written `HH:MM[:SS]`, in seconds after midnight; midnight if left out
+fn parse_time_of_day(words: &[&str]) -> Result<f64, String> {
+    let clock = match words {
+        [] => return Ok(0.0),
+        [clock] => *clock,
+        _ => return Err(format!("Expected a time of day like 09:00, not {:?}", words.join(" "))),
+    };
+    let invalid = || format!("Invalid time of day {clock:?}, expected HH:MM or HH:MM:SS");
+    let parts = clock.split(':').map(|p| p.parse::<f64>().map_err(|_| invalid())).collect::<Result<Vec<_>, _>>()?;
+    let (hours, minutes, seconds) = match parts.as_slice() {
+        [h, m] => (*h, *m, 0.0),
+        [h, m, s] => (*h, *m, *s),
+        _ => return Err(invalid()),
+    };
+    if !(0.0..24.0).contains(&hours) || !(0.0..60.0).contains(&minutes) || !(0.0..60.0).contains(&seconds) {
+        return Err(invalid());
+    }
+    Ok(hours * 3600.0 + minutes * 60.0 + seconds)
+}
+
+/// Julian dates can be given as `JD2451545.0`, `MJD51544.5`, or `JDE2451545.0` for a JD in TT. A bare number is
+/// only taken as a Julian date between `BARE_JD_RANGE`, so that a year like `2024` isn't read as a day in 4707 BC.
+fn parse_absolute(s: &str) -> Result<Base, String> {
+    let upper = s.to_uppercase();
+    let number = |n: &str| n.trim().parse::<f64>().ok()
+        .filter(|n| n.is_finite())
+        .ok_or(format!("Invalid Julian date {s:?}"));
+    if let Some(jde) = upper.strip_prefix("JDE") {
+        Ok(Base::Instant(jd_to_internal(number(jde)?)))
+    } else if let Some(jd) = upper.strip_prefix("JD") {
+        Ok(Base::Instant(utc_jd_to_tt(number(jd)?)))
+    } else if let Some(mjd) = upper.strip_prefix("MJD") {
+        Ok(Base::Instant(utc_jd_to_tt(number(mjd)? + MJD_OFFSET)))
+    } else if let Ok(jd) = s.parse::<f64>() {
+        if !jd.is_finite() {
+            return Err(format!("Invalid Julian date {s:?}"));
+        }
+        if !BARE_JD_RANGE.contains(&jd) {
+            return Err(format!("{s:?} is not a plausible Julian date; write JD{s} for one, or a date like 2024-03-01"));
+        }
+        Ok(Base::Instant(utc_jd_to_tt(jd)))
+    } else if let Some(date) = calendar::parse_date(s) {
+        match date.map_err(|e| format!("Invalid date {s:?}: {e}"))? {
+            (jd, Some(offset_minutes)) => Ok(Base::Instant(utc_jd_to_tt(jd - offset_minutes / 1440.0))),
+            (jd, None) => Ok(Base::Wall(jd)),
+        }
+    } else if let Some(time) = [&Iso8601::DEFAULT as &dyn Parsable, &Rfc2822, &Rfc3339].into_iter()
+            .find_map(|format| OffsetDateTime::parse(s, format).ok()) {
+        Ok(Base::Instant(utc_jd_to_tt(CalendarDate::from(time).julian_date())))
+    } else {
+        Err(format!("Unrecognized time {s:?}: expected now, today, tomorrow, yesterday, next <weekday>, \
+            start of next <day|week|month|year>, a date like 2024-03-01 or 2024-03-01T12:00, \
+            ISO 8601, RFC 2822, RFC 3339, JD or MJD, optionally followed by offsets like +3d or -6h"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bare_numbers_must_look_like_julian_dates() {
+        assert!(matches!(parse_absolute("2451545"), Ok(Base::Instant(_))));
+        assert!(parse_absolute("2024").is_err());
+        for s in ["NaN", "inf", "-inf", "JDnan", "MJDinf", "JDEinfinity"] {
+            assert!(parse_absolute(s).is_err(), "{s} should be rejected");
+        }
+    }
+}
@@ -0,0 +1,330 @@
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+use clap::ValueEnum;
+use time::{macros::datetime, OffsetDateTime};
+use uom::si::f64::*;
+use uom::si::time::{day, second};
+
+use crate::orbitor::{julian_centuries, J2000_JD};
+
+// The simulation's time variable is Terrestrial Time (TT), in seconds since J2000 (JD 2451545.0 TT).
+// Dates come in and go out as UTC; everything else converts explicitly through the functions below.
+
+/// TT - TAI, fixed by definition
+const TT_MINUS_TAI: f64 = 32.184;
+
+/// Noon UTC on 2000-01-01, the civil-time counterpart of J2000
+const J2000_UTC: OffsetDateTime = datetime!(2000-01-01 12:00 UTC);
+
+/// Dates from which TAI - UTC took each value, from IERS Bulletin C
+const LEAP_SECONDS: [(OffsetDateTime, f64); 28] = [
+    (datetime!(1972-01-01 0:00 UTC), 10.0),
+    (datetime!(1972-07-01 0:00 UTC), 11.0),
+    (datetime!(1973-01-01 0:00 UTC), 12.0),
+    (datetime!(1974-01-01 0:00 UTC), 13.0),
+    (datetime!(1975-01-01 0:00 UTC), 14.0),
+    (datetime!(1976-01-01 0:00 UTC), 15.0),
+    (datetime!(1977-01-01 0:00 UTC), 16.0),
+    (datetime!(1978-01-01 0:00 UTC), 17.0),
+    (datetime!(1979-01-01 0:00 UTC), 18.0),
+    (datetime!(1980-01-01 0:00 UTC), 19.0),
+    (datetime!(1981-07-01 0:00 UTC), 20.0),
+    (datetime!(1982-07-01 0:00 UTC), 21.0),
+    (datetime!(1983-07-01 0:00 UTC), 22.0),
+    (datetime!(1985-07-01 0:00 UTC), 23.0),
+    (datetime!(1988-01-01 0:00 UTC), 24.0),
+    (datetime!(1990-01-01 0:00 UTC), 25.0),
+    (datetime!(1991-01-01 0:00 UTC), 26.0),
+    (datetime!(1992-07-01 0:00 UTC), 27.0),
+    (datetime!(1993-07-01 0:00 UTC), 28.0),
+    (datetime!(1994-07-01 0:00 UTC), 29.0),
+    (datetime!(1996-01-01 0:00 UTC), 30.0),
+    (datetime!(1997-07-01 0:00 UTC), 31.0),
+    (datetime!(1999-01-01 0:00 UTC), 32.0),
+    (datetime!(2006-01-01 0:00 UTC), 33.0),
+    (datetime!(2009-01-01 0:00 UTC), 34.0),
+    (datetime!(2012-07-01 0:00 UTC), 35.0),
+    (datetime!(2015-07-01 0:00 UTC), 36.0),
+    (datetime!(2017-01-01 0:00 UTC), 37.0),
+];
+
+/// Last date the leap second table is known to hold. Later dates assume no further leap seconds,
+/// and UT1 follows the growth of the ΔT model from here.
+const LEAP_SECONDS_EXPIRE: OffsetDateTime = datetime!(2026-06-28 0:00 UTC);
+
+/// A way of counting time
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum TimeScale {
+    /// Coordinated Universal Time, civil time with leap seconds
+    Utc,
+    /// International Atomic Time
+    Tai,
+    /// Terrestrial Time, the simulation's time variable
+    Tt,
+    /// Barycentric Dynamical Time
+    Tdb,
+    /// Universal Time, following the Earth's rotation
+    Ut1,
+}
+
+impl TimeScale {
+    pub const ALL: [TimeScale; 5] = [TimeScale::Utc, TimeScale::Tai, TimeScale::Tt, TimeScale::Tdb, TimeScale::Ut1];
+
+    /// Time since noon on 2000-01-01 in this scale, from a TT time
+    pub fn since_j2000(self, time: Time) -> Time {
+        match self {
+            TimeScale::Utc => Time::new::<day>(tt_to_utc_jd(time) - J2000_JD),
+            TimeScale::Tai => tt_to_tai(time),
+            TimeScale::Tt => time,
+            TimeScale::Tdb => tt_to_tdb(time),
+            TimeScale::Ut1 => tt_to_ut1(time),
+        }
+    }
+
+    /// Julian date in this scale
+    pub fn julian_date(self, time: Time) -> f64 {
+        J2000_JD + self.since_j2000(time).get::<day>()
+    }
+}
+
+/// Observed or predicted ΔT values supplied by the user, which take precedence over the built-in model
+static DELTA_T_TABLE: OnceLock<DeltaTTable> = OnceLock::new();
+
+/// ΔT = TT - UT1 at a series of dates, interpolated linearly
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeltaTTable {
+    /// (decimal year, seconds), sorted by year
+    entries: Vec<(f64, f64)>,
+}
+
+impl DeltaTTable {
+    /// Parse lines of `year delta_t`, with the year as a decimal (e.g. 1990.5) and ΔT in seconds.
+    /// Blank lines and anything after a `#` are ignored.
+    pub fn parse(contents: &str) -> Result<DeltaTTable, String> {
+        let mut entries = Vec::new();
+        for (i, line) in contents.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let mut fields = line.split_whitespace().map(str::parse::<f64>);
+            match (fields.next(), fields.next()) {
+                (Some(Ok(year)), Some(Ok(delta_t))) => entries.push((year, delta_t)),
+                _ => return Err(format!("line {}: expected a year and ΔT in seconds, got {line:?}", i + 1)),
+            }
+        }
+        if entries.is_empty() {
+            return Err("no ΔT values found".into());
+        }
+        entries.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Ok(DeltaTTable { entries })
+    }
+
+    /// ΔT in seconds, if the year is within the table
+    pub fn get(&self, year: f64) -> Option<f64> {
+        let (first, last) = (self.entries[0], self.entries[self.entries.len() - 1]);
+        if year < first.0 || year > last.0 {
+            return None;
+        }
+        // A single row only covers its own year, with nothing to interpolate towards
+        if self.entries.len() == 1 {
+            return Some(first.1);
+        }
+        let idx = self.entries.partition_point(|(y, _)| *y <= year).max(1).min(self.entries.len() - 1);
+        let ((y0, d0), (y1, d1)) = (self.entries[idx - 1], self.entries[idx]);
+        if y1 == y0 {
+            return Some(d0);
+        }
+        Some(d0 + (d1 - d0) * (year - y0) / (y1 - y0))
+    }
+}
+
+/// Read a ΔT table file and use it for all later conversions. Can only be done once.
+pub fn load_delta_t_table(path: &Path) -> Result<(), String> {
+    let contents = fs::read_to_string(path).map_err(|e| format!("Could not read {}: {e}", path.display()))?;
+    let table = DeltaTTable::parse(&contents).map_err(|e| format!("{}: {e}", path.display()))?;
+    DELTA_T_TABLE.set(table).map_err(|_| "A ΔT table was already loaded".to_string())
+}
+
+/// Civil seconds (86400 to the day, no leap seconds) from noon UTC on 2000-01-01
+fn civil_seconds(dt: OffsetDateTime) -> f64 {
+    (dt - J2000_UTC).as_seconds_f64()
+}
+
+/// TAI - UTC in seconds, or `None` before leap seconds began in 1972
+fn tai_minus_utc(civil: f64) -> Option<f64> {
+    LEAP_SECONDS.iter().rev().find(|(start, _)| civil >= civil_seconds(*start)).map(|(_, offset)| *offset)
+}
+
+/// TT - UTC, with UTC given as civil seconds since noon on 2000-01-01. Before 1972, UTC is taken to be UT1, so this is ΔT.
+fn tt_minus_utc(civil: f64) -> Time {
+    match tai_minus_utc(civil) {
+        Some(offset) => Time::new::<second>(offset + TT_MINUS_TAI),
+        None => delta_t(Time::new::<second>(civil + delta_t_model(decimal_year(Time::new::<second>(civil))))),
+    }
+}
+
+/// UTC date to the simulation's time variable (TT)
+pub fn utc_to_tt(dt: OffsetDateTime) -> Time {
+    utc_jd_to_tt(J2000_JD + civil_seconds(dt) / 86400.0)
+}
+
+/// Julian date in UTC to the simulation's time variable (TT). Unlike `utc_to_tt`, this works for any year.
+pub fn utc_jd_to_tt(jd: f64) -> Time {
+    let civil = (jd - J2000_JD) * 86400.0;
+    Time::new::<second>(civil) + tt_minus_utc(civil)
+}
+
+/// The simulation's time variable (TT) to a Julian date in UTC
+pub fn tt_to_utc_jd(time: Time) -> f64 {
+    // TT - UTC changes slowly (or in whole seconds), so a few rounds settle it
+    let mut civil = time.get::<second>();
+    for _ in 0..3 {
+        civil = (time - tt_minus_utc(civil)).get::<second>();
+    }
+    J2000_JD + civil / 86400.0
+}
+
+pub fn tt_to_tai(time: Time) -> Time {
+    time - Time::new::<second>(TT_MINUS_TAI)
+}
+
+/// Universal Time (UT1), the Earth's rotation angle as a time, for sidereal time
+pub fn tt_to_ut1(time: Time) -> Time {
+    time - delta_t(time)
+}
+
+/// Barycentric Dynamical Time, which orbits are propagated in. It differs from TT by under 2 ms,
+/// periodically (USNO Circular 179, eq. 2.6).
+pub fn tt_to_tdb(time: Time) -> Time {
+    let t = julian_centuries(time);
+    let seconds = 0.001657 * (628.3076 * t + 6.2401).sin()
+        + 0.000022 * (575.3385 * t + 4.2970).sin()
+        + 0.000014 * (1256.6152 * t + 6.1969).sin()
+        + 0.000005 * (606.9777 * t + 4.0212).sin()
+        + 0.000005 * (52.9691 * t + 0.4444).sin()
+        + 0.000002 * (21.3299 * t + 5.5431).sin()
+        + 0.000010 * t * (628.3076 * t + 4.2490).sin();
+    time + Time::new::<second>(seconds)
+}
+
+/// ΔT = TT - UT1. A loaded table takes precedence; while leap seconds are known, UT1 is taken to be UTC
+/// (within 0.9 s); otherwise the Espenak-Meeus polynomials are used.
+pub fn delta_t(time: Time) -> Time {
+    let year = decimal_year(time);
+    if let Some(seconds) = DELTA_T_TABLE.get().and_then(|table| table.get(year)) {
+        return Time::new::<second>(seconds);
+    }
+    let civil = time.get::<second>();
+    let expire = civil_seconds(LEAP_SECONDS_EXPIRE);
+    let seconds = match tai_minus_utc(civil) {
+        Some(offset) if civil <= expire => offset + TT_MINUS_TAI,
+        Some(offset) => {
+            // Continue from the last known value at the rate the model predicts
+            offset + TT_MINUS_TAI + delta_t_model(year) - delta_t_model(decimal_year(Time::new::<second>(expire)))
+        },
+        None => delta_t_model(year),
+    };
+    Time::new::<second>(seconds)
+}
+
+/// Year with fraction, e.g. 2024.5 for early July 2024
+pub fn decimal_year(time: Time) -> f64 {
+    2000.0 + time.get::<day>() / 365.25
+}
+
+/// ΔT in seconds from the polynomial expressions of Espenak and Meeus (NASA Five Millennium Canon of Solar Eclipses)
+pub fn delta_t_model(year: f64) -> f64 {
+    let y = year;
+    let u = (y - 1820.0) / 100.0;
+    match y {
+        _ if y < -500.0 => -20.0 + 32.0 * u * u,
+        _ if y < 500.0 => {
+            let u = y / 100.0;
+            10583.6 + u * (-1014.41 + u * (33.78311 + u * (-5.952053 + u * (-0.1798452 + u * (0.022174192 + u * 0.0090316521)))))
+        },
+        _ if y < 1600.0 => {
+            let u = (y - 1000.0) / 100.0;
+            1574.2 + u * (-556.01 + u * (71.23472 + u * (0.319781 + u * (-0.8503463 + u * (-0.005050998 + u * 0.0083572073)))))
+        },
+        _ if y < 1700.0 => {
+            let t = y - 1600.0;
+            120.0 + t * (-0.9808 + t * (-0.01532 + t / 7129.0))
+        },
+        _ if y < 1800.0 => {
+            let t = y - 1700.0;
+            8.83 + t * (0.1603 + t * (-0.0059285 + t * (0.00013336 - t / 1174000.0)))
+        },
+        _ if y < 1860.0 => {
+            let t = y - 1800.0;
+            13.72 + t * (-0.332447 + t * (0.0068612 + t * (0.0041116 + t * (-0.00037436
+                + t * (0.0000121272 + t * (-0.0000001699 + t * 0.000000000875))))))
+        },
+        _ if y < 1900.0 => {
+            let t = y - 1860.0;
+            7.62 + t * (0.5737 + t * (-0.251754 + t * (0.01680668 + t * (-0.0004473624 + t / 233174.0))))
+        },
+        _ if y < 1920.0 => {
+            let t = y - 1900.0;
+            -2.79 + t * (1.494119 + t * (-0.0598939 + t * (0.0061966 - t * 0.000197)))
+        },
+        _ if y < 1941.0 => {
+            let t = y - 1920.0;
+            21.20 + t * (0.84493 + t * (-0.076100 + t * 0.0020936))
+        },
+        _ if y < 1961.0 => {
+            let t = y - 1950.0;
+            29.07 + t * (0.407 + t * (-1.0 / 233.0 + t / 2547.0))
+        },
+        _ if y < 1986.0 => {
+            let t = y - 1975.0;
+            45.45 + t * (1.067 + t * (-1.0 / 260.0 - t / 718.0))
+        },
+        _ if y < 2005.0 => {
+            let t = y - 2000.0;
+            63.86 + t * (0.3345 + t * (-0.060374 + t * (0.0017275 + t * (0.000651814 + t * 0.00002373599))))
+        },
+        _ if y < 2050.0 => {
+            let t = y - 2000.0;
+            62.92 + t * (0.32217 + t * 0.005589)
+        },
+        _ if y < 2150.0 => -20.0 + 32.0 * u * u - 0.5628 * (2150.0 - y),
+        _ => -20.0 + 32.0 * u * u,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_delta_t_tables() {
+        let table = DeltaTTable::parse("# year  ΔT\n2010.0 66.1\n\n2000.0 63.8  # J2000\n").unwrap();
+        assert_eq!(table.entries, vec![(2000.0, 63.8), (2010.0, 66.1)]);
+        assert!(DeltaTTable::parse("").is_err());
+        assert!(DeltaTTable::parse("# nothing but comments\n").is_err());
+        assert!(DeltaTTable::parse("2000.0\n").is_err());
+        assert!(DeltaTTable::parse("2000.0 sixty\n").is_err());
+    }
+
+    #[test]
+    fn interpolates_within_the_table_only() {
+        let table = DeltaTTable::parse("2000.0 63.8\n2010.0 66.1\n2020.0 69.4\n").unwrap();
+        assert_eq!(table.get(2000.0), Some(63.8));
+        assert_eq!(table.get(2020.0), Some(69.4));
+        assert_eq!(table.get(2010.0), Some(66.1));
+        assert!((table.get(2005.0).unwrap() - 64.95).abs() < 1e-9);
+        assert!((table.get(2017.5).unwrap() - 68.575).abs() < 1e-9);
+        assert_eq!(table.get(1999.9), None);
+        assert_eq!(table.get(2020.1), None);
+    }
+
+    #[test]
+    fn single_row_tables_cover_their_year() {
+        let table = DeltaTTable::parse("2000.0 63.8\n").unwrap();
+        assert_eq!(table.get(2000.0), Some(63.8));
+        assert_eq!(table.get(2000.5), None);
+        assert_eq!(table.get(1999.5), None);
+    }
+}
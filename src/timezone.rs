@@ -0,0 +1,98 @@
+use time::OffsetDateTime;
+use time_tz::{system, timezones, Offset, OffsetResult, TimeZone, Tz};
+use uom::si::f64::Time;
+
+use crate::timescales::{tt_to_utc_jd, utc_jd_to_tt};
+
+/// Julian date of the Unix epoch, 1970-01-01 00:00 UTC
+const UNIX_EPOCH_JD: f64 = 2440587.5;
+
+/// Look up a zone in the built-in IANA database, e.g. `Europe/London`
+pub fn zone_by_name(name: &str) -> Result<&'static Tz, String> {
+    if let Some(zone) = timezones::get_by_name(name) {
+        return Ok(zone);
+    }
+    let matches: Vec<&str> = timezones::find_by_name(name).iter().map(|zone| zone.name()).take(5).collect();
+    if matches.is_empty() {
+        Err(format!("Unknown time zone {name:?}, expected an IANA name like Europe/London"))
+    } else {
+        Err(format!("Unknown time zone {name:?}, did you mean one of: {}", matches.join(", ")))
+    }
+}
+
+/// The zone of this computer: `TZ` if it names a zone, otherwise the system setting
+pub fn local_zone() -> Result<&'static Tz, String> {
+    if let Some(zone) = std::env::var("TZ").ok().and_then(|name| timezones::get_by_name(name.trim_start_matches(':'))) {
+        return Ok(zone);
+    }
+    system::get_timezone().map_err(|e| format!("Could not find the local time zone ({e}); use --tz instead"))
+}
+
+pub fn utc() -> &'static Tz {
+    timezones::db::UTC
+}
+
+/// The instant of a Julian date, when it falls in the years the time crate can represent
+fn date_time(jd: f64) -> Option<OffsetDateTime> {
+    OffsetDateTime::from_unix_timestamp_nanos(((jd - UNIX_EPOCH_JD) * 86400e9).round() as i128).ok()
+}
+
+/// Offset from UTC in seconds, and its abbreviation, at a Julian date in UTC
+pub fn offset_at(zone: &Tz, jd: f64) -> (f64, String) {
+    let offset = match date_time(jd) {
+        Some(dt) => zone.get_offset_utc(&dt),
+        None => zone.get_offset_primary(),
+    };
+    (offset.to_utc().whole_seconds() as f64, offset.name().to_string())
+}
+
+/// Offset from UTC in seconds of the zone's standard time, leaving out daylight saving time, in the year from a
+/// Julian date in UTC
+pub fn standard_offset(zone: &Tz, jd: f64) -> f64 {
+    (0..12)
+        .filter_map(|month| date_time(jd + month as f64 * 30.5))
+        .map(|dt| zone.get_offset_utc(&dt))
+        .find(|offset| !offset.is_dst())
+        .map_or_else(|| offset_at(zone, jd).0, |offset| offset.to_utc().whole_seconds() as f64)
+}
+
+/// Written like `UTC+1` or `UTC-3:30`, or just `UTC`
+pub fn format_offset(seconds: f64) -> String {
+    let minutes = (seconds / 60.0).round() as i64;
+    let sign = if minutes < 0 { '-' } else { '+' };
+    match (minutes.abs() / 60, minutes.abs() % 60) {
+        (0, 0) => "UTC".to_string(),
+        (hours, 0) => format!("UTC{sign}{hours}"),
+        (hours, minutes) => format!("UTC{sign}{hours}:{minutes:02}"),
+    }
+}
+
+/// Julian date in UTC of a wall-clock time in a zone, given as a Julian date read off the clock.
+/// When clocks go back the earlier of the repeated times is used; times skipped when clocks go forward are an error.
+pub fn wall_to_utc(zone: &Tz, jd: f64) -> Result<f64, String> {
+    let Some(dt) = date_time(jd) else {
+        return Ok(jd - zone.get_offset_primary().to_utc().whole_seconds() as f64 / 86400.0);
+    };
+    match zone.get_offset_local(&dt) {
+        OffsetResult::Some(offset) | OffsetResult::Ambiguous(offset, _) => {
+            Ok(jd - offset.to_utc().whole_seconds() as f64 / 86400.0)
+        },
+        OffsetResult::None => Err(format!("That time does not exist in {}, as clocks went forward", zone.name())),
+    }
+}
+
+/// Julian date read off a clock in the zone at an instant
+pub fn wall(zone: &Tz, time: Time) -> f64 {
+    let jd = tt_to_utc_jd(time);
+    jd + offset_at(zone, jd).0 / 86400.0
+}
+
+/// The instant a clock in the zone shows a Julian date
+pub fn from_wall(zone: &Tz, jd: f64) -> Result<Time, String> {
+    wall_to_utc(zone, jd).map(utc_jd_to_tt)
+}
+
+/// Julian date of the midnight starting the day of a Julian date
+pub fn midnight(jd: f64) -> f64 {
+    (jd + 0.5).floor() - 0.5
+}